@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[program]
+pub mod test_2 {
+    use super::*;
+    /// Opens a vault of the given kind for `owner`.
+    pub fn open(ctx: Context<Open>, vault_id: u8, kind: VaultKind) -> Result<()> {
+        ctx.accounts.vault.owner = ctx.accounts.owner.key();
+        ctx.accounts.vault.kind = kind;
+        Ok(())
+    }
+
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.balance += amount;
+        Ok(())
+    }
+
+    /// Moves `amount` out of the vault,
+    /// which must hold at least that much.
+    ///
+    /// # Arguments
+    ///
+    /// * `vault_id` - id of the vault among those of the owner
+    /// * `amount` - lamports to withdraw
+    pub fn withdraw(ctx: Context<Withdraw>, vault_id: u8, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.balance -= amount;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(vault_id: u8)]
+pub struct Open<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Vault::LEN,
+        seeds = [b"vault", owner.key().as_ref(), vault_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+    /// Pays for the vault account
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    pub transfer: TokenTransferCtx<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_id: u8, amount: u64)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref(), vault_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = owner,
+        constraint = vault.balance >= amount @ VaultError::InsufficientFunds
+    )]
+    pub vault: Account<'info, Vault>,
+    pub owner: Signer<'info>,
+    pub transfer: TokenTransferCtx<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TokenTransferCtx<'info> {
+    /// CHECK: token account, validated by the token program
+    #[account(mut)]
+    pub from: AccountInfo<'info>,
+    /// CHECK: token account, validated by the token program
+    #[account(mut)]
+    pub to: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+    pub programs: ProgramsCtx<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProgramsCtx<'info> {
+    /// CHECK: only used as the cpi target
+    pub token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct Vault {
+    pub owner: Pubkey,
+    pub balance: u64,
+    pub kind: VaultKind,
+}
+
+impl Vault {
+    pub const LEN: usize = 32 + 8 + 2;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum VaultKind {
+    Personal,
+    Shared { members: u8 },
+}
+
+impl Default for VaultKind {
+    fn default() -> Self {
+        VaultKind::Personal
+    }
+}
+
+#[error_code]
+pub enum VaultError {
+    #[msg("vault balance is too low")]
+    InsufficientFunds,
+}
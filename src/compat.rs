@@ -0,0 +1,527 @@
+use crate::diff::{self, Change, InstructionDiff, ItemDiff, Status};
+use crate::types;
+use anchor_syn::idl::{Idl, IdlInstruction, IdlTypeDefinition};
+use serde::Serialize;
+use std::fmt;
+
+/// Whether a change breaks clients (and accounts) made for the old version of a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Breaking,
+    NonBreaking,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Breaking => write!(f, "breaking"),
+            Severity::NonBreaking => write!(f, "non-breaking"),
+        }
+    }
+}
+
+/// A change to the interface of a program, as far as clients are concerned. Accounts are
+/// passed by position and args are Borsh serialized in order, so their names don't matter but
+/// their order and layout do; instructions and `#[account]` types are told apart by a
+/// discriminator hashed from their name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CompatChange {
+    InstructionAdded,
+    InstructionRemoved,
+    /// An instruction with the same accounts and args as the removed `old` one
+    InstructionRenamed {
+        old: String,
+    },
+    /// A new account clients have to pass
+    AccountAdded,
+    AccountRemoved,
+    /// An account at the position of the removed `old` one, with the same mutability and signer
+    AccountRenamed {
+        old: String,
+    },
+    /// The account moved relative to the others (positions are 0-based, among the flattened
+    /// accounts of the instruction)
+    AccountOrderChanged {
+        old: usize,
+        new: usize,
+    },
+    BecameMut,
+    NoLongerMut,
+    BecameSigner,
+    NoLongerSigner,
+    ArgAdded,
+    ArgRemoved,
+    /// An arg at the position of the removed `old` one, with the same layout
+    ArgRenamed {
+        old: String,
+    },
+    /// The arg moved relative to the others (positions are 0-based)
+    ArgReordered {
+        old: usize,
+        new: usize,
+    },
+    /// The arg's type changed to one that is serialized differently
+    ArgTypeChanged {
+        old: String,
+        new: String,
+    },
+    /// The arg's type changed to one that is serialized alike, e.g. a renamed struct
+    ArgTypeRenamed {
+        old: String,
+        new: String,
+    },
+    /// A struct or enum (or `#[account]` struct) is serialized differently, e.g. has new fields
+    TypeLayoutChanged {
+        old: String,
+        new: String,
+    },
+    TypeAdded,
+    /// A struct or enum is no longer declared; args that used it are reported on their own
+    TypeRemoved,
+    /// A struct or enum with the same layout as the removed `old` one
+    TypeRenamed {
+        old: String,
+    },
+    AccountTypeAdded,
+    /// An `#[account]` struct is no longer declared, so existing accounts of it can't be read
+    AccountTypeRemoved,
+    /// An `#[account]` struct with the same layout as the removed `old` one
+    AccountTypeRenamed {
+        old: String,
+    },
+}
+
+impl CompatChange {
+    pub fn severity(&self) -> Severity {
+        match self {
+            CompatChange::InstructionAdded
+            | CompatChange::AccountRenamed { .. }
+            | CompatChange::NoLongerMut
+            | CompatChange::NoLongerSigner
+            | CompatChange::ArgRenamed { .. }
+            | CompatChange::ArgTypeRenamed { .. }
+            | CompatChange::TypeAdded
+            | CompatChange::TypeRemoved
+            | CompatChange::TypeRenamed { .. }
+            | CompatChange::AccountTypeAdded => Severity::NonBreaking,
+            CompatChange::InstructionRemoved
+            | CompatChange::InstructionRenamed { .. }
+            | CompatChange::AccountAdded
+            | CompatChange::AccountRemoved
+            | CompatChange::AccountOrderChanged { .. }
+            | CompatChange::BecameMut
+            | CompatChange::BecameSigner
+            | CompatChange::ArgAdded
+            | CompatChange::ArgRemoved
+            | CompatChange::ArgReordered { .. }
+            | CompatChange::ArgTypeChanged { .. }
+            | CompatChange::TypeLayoutChanged { .. }
+            | CompatChange::AccountTypeRemoved
+            | CompatChange::AccountTypeRenamed { .. } => Severity::Breaking,
+        }
+    }
+}
+
+impl fmt::Display for CompatChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatChange::InstructionAdded => write!(f, "instruction added"),
+            CompatChange::InstructionRemoved => write!(f, "instruction removed"),
+            CompatChange::InstructionRenamed { old } => {
+                write!(
+                    f,
+                    "instruction renamed from {}, which changes its discriminator",
+                    old
+                )
+            }
+            CompatChange::AccountAdded => write!(f, "new required account"),
+            CompatChange::AccountRemoved => write!(f, "account removed"),
+            CompatChange::AccountRenamed { old } => write!(f, "account renamed from {}", old),
+            CompatChange::AccountOrderChanged { old, new } => write!(
+                f,
+                "account order changed, from position {} to {}",
+                old + 1,
+                new + 1
+            ),
+            CompatChange::BecameMut => write!(f, "account became mut"),
+            CompatChange::NoLongerMut => write!(f, "account no longer mut"),
+            CompatChange::BecameSigner => write!(f, "account became a signer"),
+            CompatChange::NoLongerSigner => write!(f, "account no longer a signer"),
+            CompatChange::ArgAdded => write!(f, "new arg"),
+            CompatChange::ArgRemoved => write!(f, "arg removed"),
+            CompatChange::ArgRenamed { old } => write!(f, "arg renamed from {}", old),
+            CompatChange::ArgReordered { old, new } => {
+                write!(f, "arg reordered, from position {} to {}", old + 1, new + 1)
+            }
+            CompatChange::ArgTypeChanged { old, new } => {
+                write!(f, "arg type changed, {} → {}", old, new)
+            }
+            CompatChange::ArgTypeRenamed { old, new } => {
+                write!(f, "type renamed, {} → {}, with the same layout", old, new)
+            }
+            CompatChange::TypeLayoutChanged { old, new } => {
+                write!(f, "type layout changed, {} → {}", old, new)
+            }
+            CompatChange::TypeAdded => write!(f, "type added"),
+            CompatChange::TypeRemoved => write!(f, "type removed"),
+            CompatChange::TypeRenamed { old } => write!(f, "type renamed from {}", old),
+            CompatChange::AccountTypeAdded => write!(f, "account type added"),
+            CompatChange::AccountTypeRemoved => write!(f, "account type removed"),
+            CompatChange::AccountTypeRenamed { old } => write!(
+                f,
+                "account type renamed from {}, which changes its discriminator",
+                old
+            ),
+        }
+    }
+}
+
+/// A change and what it applies to: an instruction (`deposit`), one of its accounts or args
+/// (`deposit.amount`) or a type (`Vault`), named as in the new version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub path: String,
+    pub change: CompatChange,
+    pub severity: Severity,
+}
+
+impl Finding {
+    fn new(path: String, change: CompatChange) -> Self {
+        Finding {
+            path,
+            severity: change.severity(),
+            change,
+        }
+    }
+}
+
+/// The changes between two versions of a program that clients may notice, in the order of
+/// `diff::diff`, followed by those of types.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CompatReport {
+    pub name: String,
+    pub old_version: String,
+    pub new_version: String,
+    pub findings: Vec<Finding>,
+}
+
+impl CompatReport {
+    pub fn breaking(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Breaking)
+    }
+
+    /// Whether clients of the old version keep working with the new one.
+    pub fn is_compatible(&self) -> bool {
+        self.breaking().next().is_none()
+    }
+}
+
+/// A line per finding under a summary, e.g.
+/// `breaking      deposit.amount: arg type changed, u64 → u128`.
+impl fmt::Display for CompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let breaking = self.breaking().count();
+        write!(
+            f,
+            "{} {} → {}: ",
+            self.name, self.old_version, self.new_version
+        )?;
+        if self.findings.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        writeln!(
+            f,
+            "{} breaking, {} non-breaking changes",
+            breaking,
+            self.findings.len() - breaking
+        )?;
+        for finding in &self.findings {
+            let severity = finding.severity.to_string();
+            writeln!(f, "{:<14}{}: {}", severity, finding.path, finding.change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Classifies the changes from `old` to `new` by whether they break clients of `old`.
+pub fn check(old: &Idl, new: &Idl) -> CompatReport {
+    let program_diff = diff::diff(old, new);
+    let renamed = renamed_instructions(old, new, &program_diff);
+    let mut findings = vec![];
+    for ix_diff in &program_diff.instructions {
+        let path = ix_diff.name.clone();
+        match ix_diff.status {
+            Status::Added => {
+                let change = match renamed.iter().find(|(_, new)| *new == ix_diff.name) {
+                    Some((old, _)) => CompatChange::InstructionRenamed { old: old.clone() },
+                    None => CompatChange::InstructionAdded,
+                };
+                findings.push(Finding::new(path, change));
+            }
+            Status::Removed => {
+                if !renamed.iter().any(|(old, _)| *old == ix_diff.name) {
+                    findings.push(Finding::new(path, CompatChange::InstructionRemoved));
+                }
+            }
+            Status::Changed => {
+                let old_ix = instruction(old, &ix_diff.name);
+                let new_ix = instruction(new, &ix_diff.name);
+                if let (Some(old_ix), Some(new_ix)) = (old_ix, new_ix) {
+                    instruction_findings(old_ix, new_ix, ix_diff, (old, new), &mut findings);
+                }
+            }
+            Status::Unchanged => {}
+        }
+    }
+    type_findings(old, new, &mut findings);
+    CompatReport {
+        name: new.name.clone(),
+        old_version: program_diff.old_version,
+        new_version: program_diff.new_version,
+        findings,
+    }
+}
+
+/// The instruction of `idl` named `name`, if it has one.
+fn instruction<'a>(idl: &'a Idl, name: &str) -> Option<&'a IdlInstruction> {
+    idl.instructions.iter().find(|ix| ix.name == name)
+}
+
+/// Pairs of a removed and an added instruction with the same accounts and arg layouts, by
+/// their names in `old` and `new`.
+fn renamed_instructions(old: &Idl, new: &Idl, diff: &diff::ProgramDiff) -> Vec<(String, String)> {
+    let with_status = |status| {
+        diff.instructions
+            .iter()
+            .filter(move |ix| ix.status == status)
+            .map(|ix| ix.name.clone())
+    };
+    let mut added: Vec<String> = with_status(Status::Added).collect();
+    let mut renamed = vec![];
+    for old_name in with_status(Status::Removed) {
+        let old_signature = instruction(old, &old_name).map(|ix| signature(ix, old));
+        let same = |name: &String| {
+            old_signature.is_some()
+                && old_signature == instruction(new, name).map(|ix| signature(ix, new))
+        };
+        if let Some(i) = added.iter().position(same) {
+            renamed.push((old_name, added.remove(i)));
+        }
+    }
+    renamed
+}
+
+/// What clients pass to an instruction besides its name: its accounts and the layouts of its
+/// args.
+fn signature(ix: &IdlInstruction, idl: &Idl) -> (Vec<(String, bool, bool)>, Vec<String>) {
+    let accounts = diff::flatten(&ix.accounts)
+        .iter()
+        .map(|account| (account.name.clone(), account.is_mut, account.is_signer))
+        .collect();
+    let args = ix
+        .args
+        .iter()
+        .map(|arg| types::borsh_layout(&arg.ty, idl))
+        .collect();
+    (accounts, args)
+}
+
+fn instruction_findings(
+    old_ix: &IdlInstruction,
+    new_ix: &IdlInstruction,
+    ix_diff: &InstructionDiff,
+    (old, new): (&Idl, &Idl),
+    findings: &mut Vec<Finding>,
+) {
+    let path = |name: &str| format!("{}.{}", ix_diff.name, name);
+
+    let (old_accounts, new_accounts) = (
+        diff::account_paths(&old_ix.accounts),
+        diff::account_paths(&new_ix.accounts),
+    );
+    let renamed = renamed_items(
+        &old_accounts,
+        &new_accounts,
+        &ix_diff.accounts,
+        |(path, _)| path.as_str(),
+        |(_, old), (_, new)| old.is_mut == new.is_mut && old.is_signer == new.is_signer,
+    );
+    for item in &ix_diff.accounts {
+        let changes = match item.status {
+            Status::Added => vec![match renamed.iter().find(|(_, new)| *new == item.name) {
+                Some((old, _)) => CompatChange::AccountRenamed { old: old.clone() },
+                None => CompatChange::AccountAdded,
+            }],
+            Status::Removed if renamed.iter().any(|(old, _)| *old == item.name) => vec![],
+            Status::Removed => vec![CompatChange::AccountRemoved],
+            Status::Changed => item
+                .changes
+                .iter()
+                .filter_map(|change| match change {
+                    Change::BecameMut => Some(CompatChange::BecameMut),
+                    Change::NoLongerMut => Some(CompatChange::NoLongerMut),
+                    Change::BecameSigner => Some(CompatChange::BecameSigner),
+                    Change::NoLongerSigner => Some(CompatChange::NoLongerSigner),
+                    Change::Moved { old, new } => Some(CompatChange::AccountOrderChanged {
+                        old: *old,
+                        new: *new,
+                    }),
+                    Change::TypeChanged { .. } => None,
+                })
+                .collect(),
+            Status::Unchanged => vec![],
+        };
+        for change in changes {
+            findings.push(Finding::new(path(&item.name), change));
+        }
+    }
+
+    let (old_args, new_args): (Vec<_>, Vec<_>) =
+        (old_ix.args.iter().collect(), new_ix.args.iter().collect());
+    let layout = |ty, idl| types::borsh_layout(ty, idl);
+    let renamed = renamed_items(
+        &old_args,
+        &new_args,
+        &ix_diff.args,
+        |arg| arg.name.as_str(),
+        |old_arg, new_arg| layout(&old_arg.ty, old) == layout(&new_arg.ty, new),
+    );
+    for item in &ix_diff.args {
+        let changes = match item.status {
+            Status::Added => vec![match renamed.iter().find(|(_, new)| *new == item.name) {
+                Some((old, _)) => CompatChange::ArgRenamed { old: old.clone() },
+                None => CompatChange::ArgAdded,
+            }],
+            Status::Removed if renamed.iter().any(|(old, _)| *old == item.name) => vec![],
+            Status::Removed => vec![CompatChange::ArgRemoved],
+            Status::Changed => item
+                .changes
+                .iter()
+                .filter_map(|change| match change {
+                    Change::Moved { old, new } => Some(CompatChange::ArgReordered {
+                        old: *old,
+                        new: *new,
+                    }),
+                    Change::TypeChanged {
+                        old: old_label,
+                        new: new_label,
+                    } => {
+                        let old_arg = old_ix.args.iter().find(|arg| arg.name == item.name)?;
+                        let new_arg = new_ix.args.iter().find(|arg| arg.name == item.name)?;
+                        let (old_label, new_label) = (old_label.clone(), new_label.clone());
+                        Some(if layout(&old_arg.ty, old) == layout(&new_arg.ty, new) {
+                            CompatChange::ArgTypeRenamed {
+                                old: old_label,
+                                new: new_label,
+                            }
+                        } else {
+                            CompatChange::ArgTypeChanged {
+                                old: old_label,
+                                new: new_label,
+                            }
+                        })
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Status::Unchanged => vec![],
+        };
+        for change in changes {
+            findings.push(Finding::new(path(&item.name), change));
+        }
+    }
+}
+
+/// Pairs of a removed item of `old` and an added item of `new` at the same position that are
+/// the `same` otherwise, by their names.
+fn renamed_items<T>(
+    old: &[T],
+    new: &[T],
+    diffs: &[ItemDiff],
+    name: impl Fn(&T) -> &str,
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<(String, String)> {
+    let status = |item_name: &str| {
+        diffs
+            .iter()
+            .find(|diff| diff.name == item_name)
+            .map(|diff| diff.status)
+    };
+    old.iter()
+        .zip(new)
+        .filter(|(old_item, new_item)| {
+            status(name(old_item)) == Some(Status::Removed)
+                && status(name(new_item)) == Some(Status::Added)
+                && same(old_item, new_item)
+        })
+        .map(|(old_item, new_item)| (name(old_item).to_string(), name(new_item).to_string()))
+        .collect()
+}
+
+/// Changes to the `#[account]` structs and custom types, matched by name, or by layout for
+/// renamed ones.
+fn type_findings(old: &Idl, new: &Idl, findings: &mut Vec<Finding>) {
+    let defs = |idl: &Idl| -> Vec<(bool, IdlTypeDefinition)> {
+        let accounts = idl.accounts.iter().map(|def| (true, def.clone()));
+        accounts
+            .chain(idl.types.iter().map(|def| (false, def.clone())))
+            .collect()
+    };
+    let (old_defs, new_defs) = (defs(old), defs(new));
+    let find = |defs: &[(bool, IdlTypeDefinition)], name: &str| {
+        defs.iter().find(|(_, def)| def.name == name).cloned()
+    };
+    let mut renamed: Vec<&str> = vec![];
+    for (is_account, old_def) in &old_defs {
+        let old_layout = types::definition_layout(old_def, old);
+        if let Some((_, new_def)) = find(&new_defs, &old_def.name) {
+            let new_layout = types::definition_layout(&new_def, new);
+            if old_layout != new_layout {
+                let change = CompatChange::TypeLayoutChanged {
+                    old: old_layout,
+                    new: new_layout,
+                };
+                findings.push(Finding::new(new_def.name.clone(), change));
+            }
+            continue;
+        }
+        let rename = new_defs.iter().find(|(new_is_account, new_def)| {
+            new_is_account == is_account
+                && find(&old_defs, &new_def.name).is_none()
+                && !renamed.contains(&new_def.name.as_str())
+                && types::definition_layout(new_def, new) == old_layout
+        });
+        let old_name = old_def.name.clone();
+        let (path, change) = match (rename, is_account) {
+            (Some((_, new_def)), true) => {
+                renamed.push(&new_def.name);
+                let change = CompatChange::AccountTypeRenamed { old: old_name };
+                (new_def.name.clone(), change)
+            }
+            (Some((_, new_def)), false) => {
+                renamed.push(&new_def.name);
+                (
+                    new_def.name.clone(),
+                    CompatChange::TypeRenamed { old: old_name },
+                )
+            }
+            (None, true) => (old_name, CompatChange::AccountTypeRemoved),
+            (None, false) => (old_name, CompatChange::TypeRemoved),
+        };
+        findings.push(Finding::new(path, change));
+    }
+    for (is_account, new_def) in &new_defs {
+        if find(&old_defs, &new_def.name).is_none() && !renamed.contains(&new_def.name.as_str()) {
+            let change = if *is_account {
+                CompatChange::AccountTypeAdded
+            } else {
+                CompatChange::TypeAdded
+            };
+            findings.push(Finding::new(new_def.name.clone(), change));
+        }
+    }
+}
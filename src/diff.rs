@@ -0,0 +1,387 @@
+use crate::types;
+use crate::viz::Program;
+use anchor_syn::idl::{Idl, IdlAccount, IdlAccountItem, IdlAccounts, IdlField, IdlInstruction};
+use serde::Serialize;
+use std::fmt;
+
+/// How an instruction, account or arg differs between an old and a new version of a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Unchanged,
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [
+        Status::Unchanged,
+        Status::Added,
+        Status::Removed,
+        Status::Changed,
+    ];
+
+    /// What this status means, e.g. for a legend
+    pub fn description(&self) -> &'static str {
+        match self {
+            Status::Unchanged => "unchanged",
+            Status::Added => "added",
+            Status::Removed => "removed",
+            Status::Changed => "changed",
+        }
+    }
+}
+
+/// A change to an account or arg that both versions have.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    BecameMut,
+    NoLongerMut,
+    BecameSigner,
+    NoLongerSigner,
+    /// The arg's type changed, e.g. from `u8` to `u64`
+    TypeChanged {
+        old: String,
+        new: String,
+    },
+    /// The account or arg moved relative to the others that both versions have; `old` and `new`
+    /// are its (0-based) positions among the instruction's (flattened) accounts or args.
+    Moved {
+        old: usize,
+        new: usize,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::BecameMut => write!(f, "became mut"),
+            Change::NoLongerMut => write!(f, "no longer mut"),
+            Change::BecameSigner => write!(f, "became a signer"),
+            Change::NoLongerSigner => write!(f, "no longer a signer"),
+            Change::TypeChanged { old, new } => write!(f, "type {} → {}", old, new),
+            Change::Moved { old, new } => {
+                write!(f, "moved from position {} to {}", old + 1, new + 1)
+            }
+        }
+    }
+}
+
+/// How one account or arg differs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ItemDiff {
+    /// Name of the arg, or group path of the account (see `account_paths`)
+    pub name: String,
+    pub status: Status,
+    /// What changed, for a `Status::Changed` item
+    pub changes: Vec<Change>,
+}
+
+/// How one instruction and its accounts and args differ. Accounts are those of the
+/// instruction's account groups too, flattened, and matched by their group path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InstructionDiff {
+    pub name: String,
+    /// `Status::Changed` if any of its accounts or args is not unchanged
+    pub status: Status,
+    pub accounts: Vec<ItemDiff>,
+    pub args: Vec<ItemDiff>,
+}
+
+impl InstructionDiff {
+    /// The account at group `path`, e.g. `transfer.programs.systemProgram`, or just the name of
+    /// an account not in a group.
+    pub fn account(&self, path: &str) -> Option<&ItemDiff> {
+        self.accounts.iter().find(|account| account.name == path)
+    }
+
+    pub fn arg(&self, name: &str) -> Option<&ItemDiff> {
+        self.args.iter().find(|arg| arg.name == name)
+    }
+}
+
+/// How the instructions of two versions of a program differ: those of the new version in order,
+/// then the removed ones. Items of each are ordered likewise.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProgramDiff {
+    pub old_version: String,
+    pub new_version: String,
+    pub instructions: Vec<InstructionDiff>,
+}
+
+impl ProgramDiff {
+    pub fn instruction(&self, name: &str) -> Option<&InstructionDiff> {
+        self.instructions.iter().find(|ix| ix.name == name)
+    }
+
+    /// Statuses of the instructions, accounts and args, in `Status::ALL` order.
+    pub fn statuses(&self) -> Vec<Status> {
+        let statuses: Vec<Status> = self
+            .instructions
+            .iter()
+            .flat_map(|ix| {
+                let items = ix.accounts.iter().chain(&ix.args).map(|item| item.status);
+                std::iter::once(ix.status).chain(items)
+            })
+            .collect();
+        Status::ALL
+            .iter()
+            .copied()
+            .filter(|status| statuses.contains(status))
+            .collect()
+    }
+}
+
+/// How the instructions of `new` differ from those of `old`, matched by name.
+pub fn diff(old: &Idl, new: &Idl) -> ProgramDiff {
+    let mut instructions = vec![];
+    for new_ix in &new.instructions {
+        let old_ix = old.instructions.iter().find(|ix| ix.name == new_ix.name);
+        instructions.push(match old_ix {
+            Some(old_ix) => diff_instruction(old_ix, new_ix),
+            None => whole_instruction(new_ix, Status::Added),
+        });
+    }
+    for old_ix in &old.instructions {
+        if !new.instructions.iter().any(|ix| ix.name == old_ix.name) {
+            instructions.push(whole_instruction(old_ix, Status::Removed));
+        }
+    }
+    ProgramDiff {
+        old_version: old.version.clone(),
+        new_version: new.version.clone(),
+        instructions,
+    }
+}
+
+/// `new` with what `old` has and `new` doesn't added back in, and their diff: removed
+/// instructions at their old position, removed accounts at the end of their group and args at
+/// the end of their instruction's. Rendering it draws the diff in the usual columns.
+pub fn merged(old: &Program, new: &Program) -> Program {
+    let mut idl = new.idl.clone();
+    let mut source = new.source.clone();
+    for (i, old_ix) in old.idl.instructions.iter().enumerate() {
+        match idl
+            .instructions
+            .iter_mut()
+            .find(|ix| ix.name == old_ix.name)
+        {
+            Some(ix) => {
+                let paths: Vec<String> = account_paths(&ix.accounts)
+                    .into_iter()
+                    .map(|(path, _)| path)
+                    .collect();
+                for (path, account) in account_paths(&old_ix.accounts) {
+                    if !paths.contains(&path) {
+                        let groups: Vec<&str> = path.split('.').collect();
+                        insert_account(
+                            &mut ix.accounts,
+                            &groups[..groups.len() - 1],
+                            account.clone(),
+                        );
+                    }
+                }
+                for arg in &old_ix.args {
+                    if !ix.args.iter().any(|new_arg| new_arg.name == arg.name) {
+                        ix.args.push(arg.clone());
+                    }
+                }
+            }
+            None => {
+                let at = i.min(idl.instructions.len());
+                idl.instructions.insert(at, old_ix.clone());
+                if let Some(info) = old.source.instructions.get(&old_ix.name) {
+                    source
+                        .instructions
+                        .insert(old_ix.name.clone(), info.clone());
+                }
+            }
+        }
+    }
+    Program {
+        idl,
+        source,
+        diff: Some(diff(&old.idl, &new.idl)),
+        lints: vec![],
+    }
+}
+
+fn diff_instruction(old: &IdlInstruction, new: &IdlInstruction) -> InstructionDiff {
+    let accounts = diff_items(
+        &account_paths(&old.accounts),
+        &account_paths(&new.accounts),
+        |(path, _): &(String, &IdlAccount)| path.as_str(),
+        |(_, old), (_, new)| {
+            let mut changes = vec![];
+            match (old.is_mut, new.is_mut) {
+                (false, true) => changes.push(Change::BecameMut),
+                (true, false) => changes.push(Change::NoLongerMut),
+                _ => {}
+            }
+            match (old.is_signer, new.is_signer) {
+                (false, true) => changes.push(Change::BecameSigner),
+                (true, false) => changes.push(Change::NoLongerSigner),
+                _ => {}
+            }
+            changes
+        },
+    );
+    let args = diff_items(
+        &old.args.iter().collect::<Vec<_>>(),
+        &new.args.iter().collect::<Vec<_>>(),
+        |arg: &&IdlField| arg.name.as_str(),
+        |old, new| {
+            let (old, new) = (types::type_label(&old.ty), types::type_label(&new.ty));
+            if old == new {
+                vec![]
+            } else {
+                vec![Change::TypeChanged { old, new }]
+            }
+        },
+    );
+    let changed = accounts
+        .iter()
+        .chain(&args)
+        .any(|item| item.status != Status::Unchanged);
+    InstructionDiff {
+        name: new.name.clone(),
+        status: if changed {
+            Status::Changed
+        } else {
+            Status::Unchanged
+        },
+        accounts,
+        args,
+    }
+}
+
+/// Diff of an instruction only one version has, with all its accounts and args `status`.
+fn whole_instruction(ix: &IdlInstruction, status: Status) -> InstructionDiff {
+    let item = |name: &str| ItemDiff {
+        name: name.to_string(),
+        status,
+        changes: vec![],
+    };
+    InstructionDiff {
+        name: ix.name.clone(),
+        status,
+        accounts: account_paths(&ix.accounts)
+            .iter()
+            .map(|(path, _)| item(path))
+            .collect(),
+        args: ix.args.iter().map(|arg| item(&arg.name)).collect(),
+    }
+}
+
+/// Diffs of the items of `new`, then of those only `old` has, matched by `name`. Items both
+/// have get the `changes` between them, and are `Change::Moved` if their order relative to
+/// each other changed.
+fn diff_items<T>(
+    old: &[T],
+    new: &[T],
+    name: impl Fn(&T) -> &str,
+    changes: impl Fn(&T, &T) -> Vec<Change>,
+) -> Vec<ItemDiff> {
+    let position = |items: &[T], item_name: &str| items.iter().position(|i| name(i) == item_name);
+    let kept = |items: &[T], others: &[T]| -> Vec<String> {
+        items
+            .iter()
+            .map(|item| name(item).to_string())
+            .filter(|item_name| position(others, item_name).is_some())
+            .collect()
+    };
+    let (old_kept, new_kept) = (kept(old, new), kept(new, old));
+
+    let mut diffs = vec![];
+    for (n, new_item) in new.iter().enumerate() {
+        let item_name = name(new_item);
+        let diff = match position(old, item_name) {
+            Some(o) => {
+                let mut item_changes = changes(&old[o], new_item);
+                if position_in(&old_kept, item_name) != position_in(&new_kept, item_name) {
+                    item_changes.push(Change::Moved { old: o, new: n });
+                }
+                let status = if item_changes.is_empty() {
+                    Status::Unchanged
+                } else {
+                    Status::Changed
+                };
+                ItemDiff {
+                    name: item_name.to_string(),
+                    status,
+                    changes: item_changes,
+                }
+            }
+            None => ItemDiff {
+                name: item_name.to_string(),
+                status: Status::Added,
+                changes: vec![],
+            },
+        };
+        diffs.push(diff);
+    }
+    for old_item in old {
+        if position(new, name(old_item)).is_none() {
+            diffs.push(ItemDiff {
+                name: name(old_item).to_string(),
+                status: Status::Removed,
+                changes: vec![],
+            });
+        }
+    }
+    diffs
+}
+
+fn position_in(names: &[String], name: &str) -> Option<usize> {
+    names.iter().position(|n| n == name)
+}
+
+/// Accounts of `items`, with those of account groups in their place, as they are passed.
+pub(crate) fn flatten(items: &[IdlAccountItem]) -> Vec<&IdlAccount> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => vec![account],
+            IdlAccountItem::IdlAccounts(group) => flatten(&group.accounts),
+        })
+        .collect()
+}
+
+/// Accounts of `items` like `flatten`, each with its group path: the names of the groups it is
+/// in and its own, joined with dots, e.g. `transfer.programs.systemProgram`.
+pub(crate) fn account_paths(items: &[IdlAccountItem]) -> Vec<(String, &IdlAccount)> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => vec![(account.name.clone(), account)],
+            IdlAccountItem::IdlAccounts(group) => account_paths(&group.accounts)
+                .into_iter()
+                .map(|(path, account)| (format!("{}.{}", group.name, path), account))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Adds `account` at the end of the group at the path of `groups` in `items`, adding the groups
+/// that are missing.
+fn insert_account(items: &mut Vec<IdlAccountItem>, groups: &[&str], account: IdlAccount) {
+    let (name, rest) = match groups.split_first() {
+        Some(split) => split,
+        None => return items.push(IdlAccountItem::IdlAccount(account)),
+    };
+    let position = items
+        .iter()
+        .position(|item| matches!(item, IdlAccountItem::IdlAccounts(group) if group.name == *name));
+    let position = position.unwrap_or_else(|| {
+        items.push(IdlAccountItem::IdlAccounts(IdlAccounts {
+            name: name.to_string(),
+            accounts: vec![],
+        }));
+        items.len() - 1
+    });
+    if let IdlAccountItem::IdlAccounts(group) = &mut items[position] {
+        insert_account(&mut group.accounts, rest, account);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::error::VizError;
+use crate::theme::Theme;
+use crate::viz::Role;
+use anchor_syn::idl::{Idl, IdlAccount, IdlAccountItem};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Writes the graphviz graph of `idl` (see `graph`) to `out`.
+pub fn write(idl: &Idl, out: &Path, theme: &Theme) -> Result<()> {
+    std::fs::write(out, graph(idl, theme))
+        .map_err(|err| VizError::backend_write("couldn't write dot graph", err).into())
+}
+
+/// Graphviz digraph with instructions (and state methods) and accounts as nodes.
+///
+/// Accounts sharing a name are a single node, so the graph is bipartite: every edge goes from an
+/// instruction to an account and is labelled with its access mode there (signer, mut or
+/// read-only). Account groups are flattened. Account nodes take the color of the most privileged
+/// access they see in any instruction. Colors and font are those of `theme`.
+pub fn graph(idl: &Idl, theme: &Theme) -> String {
+    let state_name = idl
+        .state
+        .as_ref()
+        .map(|state| state.strct.name.clone())
+        .unwrap_or_default();
+    let instructions = idl.instructions.iter().map(|ix| (ix.name.clone(), ix));
+    let state_methods = idl.state.iter().flat_map(|state| {
+        state
+            .methods
+            .iter()
+            .map(|m| (format!("{}.{}", state_name, m.name), m))
+    });
+
+    let mut ix_nodes = String::new();
+    let mut edges = String::new();
+    // account name -> most privileged access
+    let mut accounts = BTreeMap::<String, Role>::new();
+    for (name, instruction) in instructions.chain(state_methods) {
+        writeln!(
+            ix_nodes,
+            "    \"ix:{}\" [label=\"{}\", shape=box, fillcolor=\"{}\"];",
+            escape(&name),
+            escape(&name),
+            theme.color(Role::Instruction).hex()
+        )
+        .unwrap();
+        for account in flatten(&instruction.accounts) {
+            let role = access(account);
+            writeln!(
+                edges,
+                "    \"ix:{}\" -> \"account:{}\" [label=\"{}\", color=\"{}\"{}];",
+                escape(&name),
+                escape(&account.name),
+                mode(account),
+                theme.color(role).hex(),
+                if role == Role::Immut { ", style=dashed" } else { "" }
+            )
+            .unwrap();
+            let strongest = accounts.entry(account.name.clone()).or_insert(role);
+            if privilege(role) > privilege(*strongest) {
+                *strongest = role;
+            }
+        }
+    }
+
+    let mut graph = String::from("digraph {\n");
+    graph.push_str("    rankdir=LR;\n");
+    let (background, foreground) = (theme.colors.background.hex(), theme.colors.foreground.hex());
+    let font = escape(&theme.font.family);
+    writeln!(graph, "    bgcolor=\"{}\";", background).unwrap();
+    writeln!(
+        graph,
+        "    node [style=filled, fontname=\"{}\", fontcolor=\"{}\", color=\"{}\"];",
+        font,
+        theme.colors.text.hex(),
+        foreground
+    )
+    .unwrap();
+    writeln!(
+        graph,
+        "    edge [fontname=\"{}\", fontcolor=\"{}\"];",
+        font, foreground
+    )
+    .unwrap();
+    graph.push_str(&ix_nodes);
+    for (name, role) in &accounts {
+        writeln!(
+            graph,
+            "    \"account:{}\" [label=\"{}\", shape=ellipse, fillcolor=\"{}\"];",
+            escape(name),
+            escape(name),
+            theme.color(*role).hex()
+        )
+        .unwrap();
+    }
+    graph.push_str(&edges);
+    graph.push_str("}\n");
+    graph
+}
+
+fn flatten(items: &[IdlAccountItem]) -> Vec<&IdlAccount> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => vec![account],
+            IdlAccountItem::IdlAccounts(group) => flatten(&group.accounts),
+        })
+        .collect()
+}
+
+/// Role of an account within one instruction; signing wins over mutability.
+fn access(account: &IdlAccount) -> Role {
+    if account.is_signer {
+        Role::Signer
+    } else if account.is_mut {
+        Role::Mut
+    } else {
+        Role::Immut
+    }
+}
+
+fn mode(account: &IdlAccount) -> &'static str {
+    match (account.is_signer, account.is_mut) {
+        (true, true) => "signer, mut",
+        (true, false) => "signer",
+        (false, true) => "mut",
+        (false, false) => "read-only",
+    }
+}
+
+fn privilege(role: Role) -> u8 {
+    match role {
+        Role::Signer => 2,
+        Role::Mut => 1,
+        _ => 0,
+    }
+}
+
+/// Escapes characters that would end a quoted dot id or label.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+/// Everything that can go wrong while generating a visualization.
+/// Each kind maps to its own process exit code (see `exit_code`) so scripts can branch on it.
+#[derive(Debug)]
+pub enum VizError {
+    /// None of the searched paths contain an anchor program
+    ProgramNotFound { searched: Vec<String> },
+    /// anchor-syn (or serde, for idl json) couldn't turn the input into an Idl
+    IdlParse { path: String, source: anyhow::Error },
+    /// Parsing failed both without and with the seeds feature
+    SeedsRetryExhausted {
+        path: String,
+        without_seeds: anyhow::Error,
+        with_seeds: anyhow::Error,
+    },
+    /// A drawing operation or writing the output file failed
+    BackendWrite { what: String, message: String },
+    /// Width (accounts, arguments per instruction column) must be at least 1
+    InvalidWidth(usize),
+    /// Output path template can't be used, e.g. it lacks `{name}` when visualizing a whole workspace
+    InvalidOutput(String),
+    /// A theme (`--theme` or the `[viz]` section of Anchor.toml) couldn't be read or is invalid
+    InvalidTheme { theme: String, message: String },
+    /// Some programs of a workspace couldn't be visualized (the others were)
+    ProgramsFailed { failed: Vec<String>, total: usize },
+    /// A git revision couldn't be found or checked out
+    Git { rev: String, message: String },
+    /// The output format can't show this, e.g. a diff as mermaid; `supported` lists those that can
+    UnsupportedFormat {
+        format: String,
+        what: String,
+        supported: String,
+    },
+    /// A new version of a program breaks clients of the old one (see `compat::check`)
+    BreakingChanges { count: usize },
+    /// Linting a program found pitfalls (see `lint::lint`)
+    LintFindings { count: usize },
+}
+
+impl VizError {
+    /// Wraps a drawing backend error, describing what was being drawn.
+    pub fn backend_write(what: &str, err: impl fmt::Display) -> Self {
+        VizError::BackendWrite {
+            what: what.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    /// Process exit code for this kind of failure.
+    /// 1 is left for any other error, 2 is what clap uses for bad usage.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VizError::InvalidWidth(_)
+            | VizError::InvalidOutput(_)
+            | VizError::InvalidTheme { .. }
+            | VizError::UnsupportedFormat { .. } => 2,
+            VizError::ProgramNotFound { .. } => 3,
+            VizError::IdlParse { .. } => 4,
+            VizError::SeedsRetryExhausted { .. } => 5,
+            VizError::BackendWrite { .. } => 6,
+            VizError::ProgramsFailed { .. } => 7,
+            VizError::Git { .. } => 8,
+            VizError::BreakingChanges { .. } => 9,
+            VizError::LintFindings { .. } => 10,
+        }
+    }
+}
+
+impl fmt::Display for VizError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VizError::ProgramNotFound { searched } => write!(
+                f,
+                "no program found (searched {}). cd into your program's directory or try anchor-viz -p PROGRAM",
+                searched.join(", ")
+            ),
+            VizError::IdlParse { path, .. } => write!(f, "couldn't parse an idl from {}", path),
+            VizError::SeedsRetryExhausted {
+                path,
+                without_seeds,
+                with_seeds,
+            } => write!(
+                f,
+                "couldn't parse {} without seeds ({:#}) nor with seeds ({:#})",
+                path, without_seeds, with_seeds
+            ),
+            VizError::BackendWrite { what, message } => write!(f, "{}: {}", what, message),
+            VizError::InvalidWidth(width) => {
+                write!(f, "invalid width {}: must be at least 1", width)
+            }
+            VizError::InvalidOutput(template) => write!(
+                f,
+                "invalid output {}: must contain {{name}} to write one file per program",
+                template
+            ),
+            VizError::InvalidTheme { theme, message } => {
+                write!(f, "invalid theme {}: {}", theme, message)
+            }
+            VizError::ProgramsFailed { failed, total } => write!(
+                f,
+                "{} of {} programs failed: {}",
+                failed.len(),
+                total,
+                failed.join(", ")
+            ),
+            VizError::Git { rev, message } => {
+                write!(f, "couldn't read revision {}: {}", rev, message)
+            }
+            VizError::UnsupportedFormat {
+                format,
+                what,
+                supported,
+            } => write!(
+                f,
+                "{} can't be written as {}, use {}",
+                what, format, supported
+            ),
+            VizError::BreakingChanges { count } => {
+                write!(f, "{} breaking changes", count)
+            }
+            VizError::LintFindings { count } => write!(f, "{} lint warnings", count),
+        }
+    }
+}
+
+/// The cause of an `IdlParse`, so that it shows in the error's chain (e.g. with `{:#}`).
+impl std::error::Error for VizError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VizError::IdlParse { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Exit code for any error returned by anchor-viz: the `VizError` kind's code, or 1 otherwise.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<VizError>()
+        .map_or(1, VizError::exit_code)
+}
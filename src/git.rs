@@ -0,0 +1,126 @@
+use crate::error::VizError;
+use anyhow::Result;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Checkouts made by this process so far, to name their directories.
+static CHECKOUTS: AtomicUsize = AtomicUsize::new(0);
+
+/// The tree of a git revision, checked out into a temporary directory that is removed on drop.
+#[derive(Debug)]
+pub struct Checkout {
+    /// Temporary directory holding the tree, named like the repository (and the index it was
+    /// checked out with)
+    root: PathBuf,
+    /// Directory of the tree that corresponds to the one the checkout was made from
+    dir: PathBuf,
+    /// The checked out tree, i.e. the root of the repository as of the revision
+    tree: PathBuf,
+}
+
+impl Checkout {
+    /// Checks out revision `rev` of the git repository at `dir` into a temporary directory,
+    /// without touching the repository's work tree or index. `dir` has to be in the revision.
+    pub fn new(dir: &Path, rev: &str) -> Result<Checkout> {
+        let not_found = || {
+            let message = format!("not a revision of a git repository at {}", dir.display());
+            git_error(rev, message)
+        };
+        // a leading - would be taken for an option
+        if rev.starts_with('-') {
+            return Err(not_found().into());
+        }
+        let verify = format!("{}^{{commit}}", rev);
+        let commit =
+            git(dir, &["rev-parse", "--verify", "--quiet", &verify]).map_err(|_| not_found())?;
+        let prefix =
+            git(dir, &["rev-parse", "--show-prefix"]).map_err(|err| git_error(rev, err))?;
+        // e.g. commit:programs/my_program/, which the program is read from
+        let subtree = format!("{}:{}", commit, prefix);
+        git(dir, &["rev-parse", "--verify", "--quiet", &subtree]).map_err(|_| {
+            let message = format!("{} isn't in that revision", dir.display());
+            git_error(rev, message)
+        })?;
+        let top_level =
+            git(dir, &["rev-parse", "--show-toplevel"]).map_err(|err| git_error(rev, err))?;
+        // the tree is named like the repository, so that directory names match those of `dir`
+        let name = Path::new(&top_level)
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_else(|| "tree".into());
+
+        let root = std::env::temp_dir().join(format!(
+            "anchor-viz-{}-{}",
+            std::process::id(),
+            CHECKOUTS.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&root)?;
+        let tree = root.join(name);
+        let checkout = Checkout {
+            dir: tree.join(&prefix),
+            tree: tree.clone(),
+            root,
+        };
+        // a throwaway index, so that the repository's own isn't changed
+        let index = checkout.root.join("index");
+        let tree = format!("--prefix={}/", tree.display());
+        let steps: [&[&str]; 2] = [&["read-tree", &commit], &["checkout-index", "--all", &tree]];
+        for args in steps {
+            let status = Command::new("git")
+                .current_dir(dir)
+                .env("GIT_INDEX_FILE", &index)
+                .args(args)
+                .output()?;
+            if !status.status.success() {
+                let stderr = String::from_utf8_lossy(&status.stderr).trim().to_string();
+                return Err(git_error(rev, stderr).into());
+            }
+        }
+        Ok(checkout)
+    }
+
+    /// Directory of the checked out tree that corresponds to the directory it was made from.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the checked out `path` in the repository, e.g. `programs/my_program/src/lib.rs`,
+    /// which stays meaningful once the checkout is removed. Paths outside of it are kept.
+    pub fn repo_path(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.tree) {
+            Ok(relative) => relative
+                .components()
+                .filter(|component| *component != Component::CurDir)
+                .collect(),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Output of `git <args>` in `dir`, trimmed, or its error output if it failed.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|err| format!("couldn't run git: {}", err))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+fn git_error(rev: &str, message: impl std::fmt::Display) -> VizError {
+    VizError::Git {
+        rev: rev.to_string(),
+        message: message.to_string(),
+    }
+}
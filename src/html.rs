@@ -0,0 +1,154 @@
+use crate::error::VizError;
+use crate::layout;
+use crate::source::{AccountsInfo, BADGES};
+use crate::theme::Theme;
+use crate::types;
+use crate::viz::{Fit, Program, Role};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::Path;
+
+// Page skeleton with the client side rendering.
+// `/*THEME*/`, `/*WIDTH*/`, `/*FIT*/`, `/*IDL*/`, `/*SOURCE*/` and `/*TYPES*/` are filled in
+// by `explorer`.
+const EXPLORER_TEMPLATE: &str = include_str!("explorer.html");
+
+/// Writes a single, self-contained html file to `out` with the idl embedded as json and
+/// an interactive explorer of its instructions: instructions can be searched, collapsed and
+/// filtered by account name, and hovering an account highlights every account of the same
+/// name in the other instructions.
+///
+/// The explorer walks the same model as the png/svg: signers, then mutable and immutable
+/// accounts (with account groups nested), then args, `width` boxes to a row. The declared types
+/// and constraints of accounts from the program `source` are shown on their boxes, with a legend
+/// of the constraint badges in use.
+///
+/// Below the instructions, the account structs and custom types are listed with their fields
+/// and sizes; args and account types that refer to one link to it.
+///
+/// Names too long for their box are wrapped (after underscores where possible), or with
+/// `Fit::Widen` the boxes grow to fit them.
+///
+/// A legend of the box colors lists the roles that appear, and doc comments from the source
+/// show as tooltips on their boxes. The page takes the colors, font
+/// family, box width and spacing of `theme`.
+pub fn explorer(
+    program: &Program,
+    out: &Path,
+    width: usize,
+    fit: Fit,
+    theme: &Theme,
+) -> Result<()> {
+    let (idl, source) = (&program.idl, &program.source);
+    // `</` inside the json would close the script tag early
+    let json = serde_json::to_string(idl)?.replace("</", "<\\/");
+    let codes = source.badge_codes();
+    let source_json = json!({
+        "instructions": source
+            .instructions
+            .iter()
+            .map(|(name, info)| (name.clone(), accounts_json(info)))
+            .collect::<serde_json::Map<_, _>>(),
+        "docs": source.docs,
+        "roles": layout::roles_present(idl)
+            .iter()
+            .map(|role| json!([role.name(), role.description()]))
+            .collect::<Vec<_>>(),
+        "legend": BADGES
+            .iter()
+            .filter(|(code, _, _)| codes.contains(*code))
+            .map(|(code, _, description)| json!([code, description]))
+            .collect::<Vec<_>>(),
+    })
+    .to_string()
+    .replace("</", "<\\/");
+    let types_json = serde_json::to_string(&types::type_defs(program))?.replace("</", "<\\/");
+    let theme_css = theme_css(theme);
+
+    let html = EXPLORER_TEMPLATE
+        .replace("/*THEME*/", &theme_css)
+        .replace("/*WIDTH*/", &width.to_string())
+        .replace(
+            "/*FIT*/",
+            match fit {
+                Fit::Shrink => "shrink",
+                Fit::Widen => "widen",
+            },
+        )
+        .replace("/*IDL*/", &json)
+        .replace("/*SOURCE*/", &source_json)
+        .replace("/*TYPES*/", &types_json);
+    std::fs::write(out, html)
+        .map_err(|err| VizError::backend_write("couldn't write html explorer", err).into())
+}
+
+/// Css custom properties of `theme`: `--<role>` fills, `--background`, `--font`, `--box-width`, ...
+fn theme_css(theme: &Theme) -> String {
+    let (colors, font, sizes) = (&theme.colors, &theme.font, &theme.sizes);
+    let roles = Role::ALL
+        .iter()
+        .map(|role| (role.name(), theme.color(*role).hex()));
+    let colors = [
+        ("background", colors.background),
+        ("foreground", colors.foreground),
+        ("text", colors.text),
+        ("unchecked", colors.unchecked),
+        ("card", colors.card),
+    ];
+    let sizes = [
+        ("title-size", font.title),
+        ("small-size", font.small),
+        ("box-width", sizes.box_width as u32),
+        ("spacing", sizes.spacing as u32),
+        ("separator", sizes.separator as u32),
+    ];
+    roles
+        .chain(colors.iter().map(|(name, color)| (*name, color.hex())))
+        .chain(sizes.iter().map(|(name, px)| (*name, format!("{}px", px))))
+        .chain([("font", css_font_family(&font.family))])
+        .map(|(name, value)| format!("--{}: {};", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `family` as a css font-family: generic families as is, others quoted with a monospace fallback.
+fn css_font_family(family: &str) -> String {
+    const GENERIC: &[&str] = &[
+        "monospace",
+        "serif",
+        "sans-serif",
+        "cursive",
+        "fantasy",
+        "system-ui",
+    ];
+    if GENERIC.contains(&family) {
+        family.to_string()
+    } else {
+        format!("{:?}, monospace", family.replace(['<', '>'], ""))
+    }
+}
+
+/// Accounts in `info` as `{"fields": {name: {"type", "unchecked", "badges", "doc"}},
+/// "groups": {name: ..}}`.
+fn accounts_json(info: &AccountsInfo) -> Value {
+    json!({
+        "fields": info
+            .fields
+            .iter()
+            .map(|(name, field)| {
+                let field = json!({
+                    "type": field.ty,
+                    "unchecked": field.is_unchecked(),
+                    "badges": field.badges(),
+                    "doc": field.doc,
+                });
+                (name.clone(), field)
+            })
+            .collect::<serde_json::Map<_, _>>(),
+        "groups": info
+            .groups
+            .iter()
+            .map(|(name, group)| (name.clone(), accounts_json(group)))
+            .collect::<serde_json::Map<_, _>>(),
+    })
+}
@@ -0,0 +1,1564 @@
+use crate::diff::{self, InstructionDiff, ItemDiff, Status};
+use crate::lint::Finding;
+use crate::source::{AccountsInfo, InstructionDocs, SourceInfo, BADGES};
+use crate::theme::Theme;
+use crate::types::{self, BorshSize, TypeDef, TypeField, TypeKind, DISCRIMINATOR_SIZE};
+use crate::viz::{Fit, Program, Role};
+use anchor_syn::idl::{
+    Idl, IdlAccount, IdlAccountItem, IdlAccounts, IdlInstruction, IdlPda, IdlSeed,
+};
+use plotters::style::{FontDesc, FontFamily, FontStyle};
+use serde::Serialize;
+
+// height of the label row atop an account group
+pub const GROUP_LABEL_PX_HEIGHT: usize = 30;
+// size of the derived-address badge on PDA accounts
+pub const PDA_BADGE_PX_WIDTH: usize = 30;
+pub const BADGE_PX_HEIGHT: usize = 13;
+// size of the seeds listed under PDA accounts
+pub const SEED_LINE_PX_HEIGHT: usize = 18;
+// horizontal inset of an account group border per level of nesting
+// (kept within the theme's spacing so borders don't run into boxes)
+pub const GROUP_INSET: usize = 2;
+// constraint badges: one line of them under the account name, wrapped as needed
+pub const BADGE_LINE_PX_HEIGHT: usize = 16;
+pub const BADGE_CHAR_PX_WIDTH: usize = 6;
+pub const BADGE_GAP: usize = 3;
+// declared anchor type, on a line under the account name
+pub const TYPE_LINE_PX_HEIGHT: usize = 18;
+// constraints legend at the bottom of the figure
+pub const LEGEND_LINE_PX_HEIGHT: usize = 20;
+pub const LEGEND_CHAR_PX_WIDTH: usize = 7;
+// role legend in the header: one line of color swatches and what they stand for, wrapped as
+// needed (every line past the first grows the header)
+pub const ROLE_LINE_PX_HEIGHT: usize = 20;
+pub const ROLE_SWATCH_PX_WIDTH: usize = 14;
+pub const ROLE_ENTRY_GAP: usize = 16;
+// doc comments footnoted under the columns
+pub const FOOTNOTE_LINE_PX_HEIGHT: usize = 18;
+// label row atop the account struct and custom type cards
+pub const TYPES_LABEL_PX_HEIGHT: usize = 30;
+
+/// Pixel rectangle, from its top left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Rect {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn right(&self) -> usize {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> usize {
+        self.top + self.height
+    }
+
+    pub fn center_x(&self) -> usize {
+        self.left + self.width / 2
+    }
+}
+
+/// How a line of text is anchored horizontally on its `x`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    Center,
+}
+
+/// A line of monospace text, vertically centered on `y`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Line {
+    pub text: String,
+    pub x: usize,
+    pub y: usize,
+    pub align: Align,
+    /// Font size, possibly shrunk from the usual size to fit
+    pub size: u32,
+}
+
+/// A small tag with white on black text, e.g. a constraint badge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Badge {
+    pub text: String,
+    pub rect: Rect,
+}
+
+/// PDA marking of an account box: its badge and, if requested, its seeds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Pda {
+    pub badge: Rect,
+    pub seeds: Vec<Line>,
+}
+
+/// A colored box: an instruction, account or argument.
+/// The label and name are fitted into the top `box_height` of the box (see `fit_text`);
+/// anything else (type, badges, seeds) goes below, in the box stretched to fit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LayoutBox {
+    pub role: Role,
+    pub label: String,
+    pub name: String,
+    /// Group path of an account, e.g. `transfer.programs.systemProgram` (see
+    /// `diff::account_paths`); the name otherwise
+    pub path: String,
+    pub rect: Rect,
+    /// Lines of the label, as fitted
+    pub label_lines: Vec<Line>,
+    /// Lines of the name, as fitted
+    pub name_lines: Vec<Line>,
+    /// Declared anchor type, if known from the source
+    #[serde(rename = "type")]
+    pub ty: Option<Line>,
+    /// Whether anchor doesn't check this account (UncheckedAccount, AccountInfo)
+    pub unchecked: bool,
+    pub badges: Vec<Badge>,
+    pub pda: Option<Pda>,
+    /// Doc comment from the source, e.g. for a tooltip
+    pub doc: Option<String>,
+    /// Number of the footnote holding the doc comment, marked after the label, if footnoted
+    pub footnote: Option<usize>,
+    /// How the box changed, in the layout of a diff (see `diff::merged`)
+    pub diff: Option<ItemDiff>,
+    /// Messages of the lint findings on the box (see `lint::lint`)
+    pub lints: Vec<String>,
+    /// Badge marking a box with lint findings, in its top right corner (left of a PDA badge)
+    pub warning: Option<Badge>,
+}
+
+impl LayoutBox {
+    fn new(
+        role: Role,
+        label: impl Into<String>,
+        name: impl Into<String>,
+        rect: Rect,
+        theme: &Theme,
+    ) -> Self {
+        let (label, name) = (label.into(), name.into());
+        let (label_lines, name_lines) = box_text(&label, &name, rect, theme);
+        LayoutBox {
+            role,
+            label,
+            path: name.clone(),
+            name,
+            rect,
+            label_lines,
+            name_lines,
+            ty: None,
+            unchecked: false,
+            badges: vec![],
+            pda: None,
+            doc: None,
+            footnote: None,
+            diff: None,
+            lints: vec![],
+            warning: None,
+        }
+    }
+
+    /// Text of this box's footnote: what changed in a diff, or else its doc comment.
+    fn note(&self) -> Option<String> {
+        match &self.diff {
+            Some(diff) if diff.changes.is_empty() => None,
+            Some(diff) => Some(
+                diff.changes
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            None => self.doc.as_ref().map(|doc| doc.replace('\n', " ")),
+        }
+    }
+
+    /// Marks this box with footnote `number`, refitting its text.
+    fn set_footnote(&mut self, number: usize, theme: &Theme) {
+        let label = format!("{} [{}]", self.label, number);
+        let (label_lines, name_lines) = box_text(&label, &self.name, self.rect, theme);
+        self.label_lines = label_lines;
+        self.name_lines = name_lines;
+        self.footnote = Some(number);
+    }
+}
+
+/// A labeled border around the accounts of a composite account group.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Group {
+    pub name: String,
+    pub rect: Rect,
+    /// The name, fitted into the label row
+    pub label: Line,
+    /// Nesting level, 0 for a group directly in the instruction's accounts
+    pub depth: usize,
+}
+
+/// One instruction (or state method) column.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Column {
+    pub rect: Rect,
+    pub instruction: LayoutBox,
+    pub signers: Vec<LayoutBox>,
+    /// Accounts at every level of nesting; their groups are in `groups`
+    pub accounts: Vec<LayoutBox>,
+    pub groups: Vec<Group>,
+    pub args: Vec<LayoutBox>,
+    /// Doc comments of the boxes, numbered as their footnotes, if footnoted
+    pub footnotes: Vec<Line>,
+}
+
+impl Column {
+    /// Boxes of the column: instruction, signers, accounts, then args.
+    pub fn boxes(&self) -> impl Iterator<Item = &LayoutBox> {
+        std::iter::once(&self.instruction)
+            .chain(&self.signers)
+            .chain(&self.accounts)
+            .chain(&self.args)
+    }
+}
+
+/// An entry of the constraints legend: the badge and what it stands for.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LegendEntry {
+    pub badge: Badge,
+    pub description: Line,
+}
+
+/// An entry of the role legend: a swatch of the role's color and what the role stands for.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RoleLegendEntry {
+    pub role: Role,
+    pub swatch: Rect,
+    pub description: Line,
+}
+
+/// An entry of the status legend of a diff: a swatch of the status's color and its description.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StatusLegendEntry {
+    pub status: Status,
+    pub swatch: Rect,
+    pub description: Line,
+}
+
+/// Legend of the constraint badges used in the figure.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Legend {
+    pub rect: Rect,
+    pub label: Line,
+    pub entries: Vec<LegendEntry>,
+}
+
+/// An account struct or custom type: its kind and name, like a box, then lines for its size,
+/// fields (or variants) and where it's used.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TypeCard {
+    pub kind: TypeKind,
+    pub label: String,
+    pub name: String,
+    pub rect: Rect,
+    pub label_lines: Vec<Line>,
+    pub name_lines: Vec<Line>,
+    pub lines: Vec<Line>,
+}
+
+/// Panel of type cards below the instruction columns, under a separator.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TypesPanel {
+    pub rect: Rect,
+    pub separator: Rect,
+    pub label: Line,
+    pub cards: Vec<TypeCard>,
+}
+
+/// Everything a renderer draws, positioned in pixels.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Layout {
+    pub width: usize,
+    pub height: usize,
+    pub title: Line,
+    pub version: Line,
+    /// Legend of the box colors, under the version, for the roles that appear in the figure
+    pub roles: Vec<RoleLegendEntry>,
+    /// Legend of the box colors of a diff, for the statuses that appear, instead of the roles
+    pub statuses: Vec<StatusLegendEntry>,
+    /// Vertical lines between columns
+    pub separators: Vec<Rect>,
+    pub columns: Vec<Column>,
+    /// Account structs and custom types, if the idl has any
+    pub types: Option<TypesPanel>,
+    pub legend: Option<Legend>,
+    /// Theme the layout was made with, its box width as widened to fit
+    pub theme: Theme,
+}
+
+/// Lays out the visualization of `program`: a header, then a column per instruction and
+/// state method with its signers, accounts (mutable first, account groups nested) and args,
+/// `width` boxes to a row. PDA seeds are listed if `show_seeds` is set.
+/// Boxes keep the doc comments of the source, which are listed under their column as numbered
+/// footnotes if `footnotes` is set.
+/// A program merged from two versions (see `diff::merged`) is laid out as their diff: boxes
+/// keep how they changed, and what changed is footnoted instead of the doc comments.
+/// The account structs and custom types of the program are listed below the columns.
+/// The header holds a legend of the box colors of the roles that appear.
+///
+/// Text that doesn't fit its box is shrunk, wrapped or cut short (see `fit_text`), unless
+/// `fit` is `Fit::Widen`: then boxes are widened to fit the widest name.
+///
+/// Colors, font and sizes come from `theme`, which the layout keeps for its renderers
+/// (with the box width as widened).
+pub fn layout(
+    program: &Program,
+    width: usize,
+    show_seeds: bool,
+    footnotes: bool,
+    fit: Fit,
+    theme: &Theme,
+) -> Layout {
+    let idl = &program.idl;
+    let state_name = idl
+        .state
+        .as_ref()
+        .map(|state| state.strct.name.clone())
+        .unwrap_or_default();
+    let mut instructions: Vec<(&str, String, &IdlInstruction)> = idl
+        .instructions
+        .iter()
+        .map(|ix| ("Instruction:", ix.name.clone(), ix))
+        .collect();
+    if let Some(state) = &idl.state {
+        instructions.extend(
+            state
+                .methods
+                .iter()
+                .map(|m| ("State Method:", format!("{}.{}", state_name, m.name), m)),
+        );
+    }
+    let columns = instructions.len();
+
+    let mut theme = theme.clone();
+    theme.sizes.box_width = match fit {
+        Fit::Shrink => theme.sizes.box_width,
+        Fit::Widen => {
+            let widest = instructions
+                .iter()
+                .map(|(_, name, instruction)| {
+                    let info = program.source.accounts(&instruction.name);
+                    widest_text(name, instruction, info, &theme)
+                })
+                .max()
+                .unwrap_or(0);
+            theme.sizes.box_width.max(widest + 2 * theme.sizes.spacing)
+        }
+    };
+
+    // Figure width: columns of `width` boxes, with separators in between. A program without
+    // instructions gets the width of one, for its title and types.
+    let fig_width = (theme.sizes.box_width + theme.sizes.spacing) * width * columns.max(1)
+        + theme.sizes.spacing * columns.max(1)
+        + columns.saturating_sub(1) * theme.sizes.separator;
+
+    // The legend of the box colors goes at the bottom of the header, which grows if it takes
+    // several lines. In a diff, boxes are colored by how they changed instead of their role.
+    let (roles, statuses) = match &program.diff {
+        Some(diff) => (vec![], diff.statuses()),
+        None => (roles_present(idl), vec![]),
+    };
+    let descriptions: Vec<&str> = roles
+        .iter()
+        .map(Role::description)
+        .chain(statuses.iter().map(Status::description))
+        .collect();
+    let color_rows = color_legend_rows(&descriptions, fig_width, &theme);
+    let color_legend_top = theme.sizes.header - theme.sizes.spacing - ROLE_LINE_PX_HEIGHT;
+    theme.sizes.header += color_rows.len().saturating_sub(1) * ROLE_LINE_PX_HEIGHT;
+    let theme = &theme;
+    let mut role_entries = vec![];
+    let mut status_entries = vec![];
+    let color_entries = layout_color_legend(
+        &color_rows,
+        &descriptions,
+        color_legend_top,
+        fig_width,
+        theme,
+    );
+    for (i, swatch, description) in color_entries {
+        match roles.get(i) {
+            Some(role) => role_entries.push(RoleLegendEntry {
+                role: *role,
+                swatch,
+                description,
+            }),
+            None => status_entries.push(StatusLegendEntry {
+                status: statuses[i - roles.len()],
+                swatch,
+                description,
+            }),
+        }
+    }
+
+    let mut layout_columns = vec![];
+    let mut column_height = 0;
+    for (i, (label, name, instruction)) in instructions.iter().enumerate() {
+        let info = program.source.accounts(&instruction.name);
+        column_height = column_height.max(column_section_height(
+            instruction,
+            info,
+            width,
+            theme,
+            show_seeds,
+        ));
+        layout_columns.push(layout_column(
+            i,
+            label,
+            name,
+            instruction,
+            info,
+            program.source.docs(&instruction.name),
+            width,
+            theme,
+            show_seeds,
+        ));
+    }
+
+    if let Some(diff) = &program.diff {
+        for (column, (_, _, instruction)) in layout_columns.iter_mut().zip(&instructions) {
+            if let Some(ix_diff) = diff.instruction(&instruction.name) {
+                mark_diff(column, ix_diff);
+            }
+        }
+    }
+
+    // instructions come first, state methods have no findings
+    for (column, instruction) in layout_columns.iter_mut().zip(&idl.instructions) {
+        let findings = program
+            .lints
+            .iter()
+            .filter(|lint| lint.instruction == instruction.name);
+        for finding in findings {
+            mark_lint(column, finding);
+        }
+    }
+
+    let legend_rows = legend_rows(&program.source, fig_width, theme);
+    let legend_height = match legend_rows.len() {
+        0 => 0,
+        rows => rows * LEGEND_LINE_PX_HEIGHT + theme.sizes.spacing,
+    };
+    let columns_bottom = column_height
+        + theme.sizes.header
+        + 3 * theme.sizes.spacing
+        + theme.sizes.box_height
+        + 2 * theme.sizes.spacing;
+
+    // Footnotes (of what changed, in a diff) go below the boxes of the longest column
+    let footnotes_top = columns_bottom - 2 * theme.sizes.spacing;
+    let footnote_lines = if footnotes || program.diff.is_some() {
+        layout_columns
+            .iter_mut()
+            .map(|column| layout_footnotes(column, footnotes_top, theme))
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let columns_bottom = match footnote_lines {
+        0 => columns_bottom,
+        lines => columns_bottom + lines * FOOTNOTE_LINE_PX_HEIGHT + theme.sizes.spacing,
+    };
+    let types = layout_types(&types::type_defs(program), columns_bottom, fig_width, theme);
+    let types_height = types.as_ref().map(|types| types.rect.height).unwrap_or(0);
+    let fig_height = columns_bottom + types_height + legend_height;
+    let legend_top = fig_height - legend_height;
+
+    let stride = column_stride(width, theme);
+    let separators = (1..columns)
+        .map(|i| Rect {
+            left: stride * i - theme.sizes.separator,
+            top: theme.sizes.header + theme.sizes.spacing,
+            width: theme.sizes.separator,
+            height: columns_bottom
+                - 3 * theme.sizes.spacing
+                - (theme.sizes.header + theme.sizes.spacing),
+        })
+        .collect();
+    for column in layout_columns.iter_mut() {
+        column.rect.height = columns_bottom - 3 * theme.sizes.spacing - column.rect.top;
+    }
+
+    let legend = (!legend_rows.is_empty()).then(|| Legend {
+        rect: Rect {
+            left: 0,
+            top: legend_top,
+            width: fig_width,
+            height: legend_height,
+        },
+        label: Line {
+            text: "Constraints:".to_string(),
+            x: theme.sizes.spacing,
+            y: legend_top + LEGEND_LINE_PX_HEIGHT / 2,
+            align: Align::Left,
+            size: theme.font.small,
+        },
+        entries: legend_rows
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| {
+                let line_top = legend_top + LEGEND_LINE_PX_HEIGHT * r;
+                row.iter().map(move |(offset, code, description)| {
+                    let left = theme.sizes.spacing + offset;
+                    let badge = Badge {
+                        text: code.to_string(),
+                        rect: Rect {
+                            left,
+                            top: line_top + (LEGEND_LINE_PX_HEIGHT - BADGE_PX_HEIGHT) / 2,
+                            width: badge_px_width(code),
+                            height: BADGE_PX_HEIGHT,
+                        },
+                    };
+                    LegendEntry {
+                        description: Line {
+                            text: description.to_string(),
+                            x: badge.rect.right() + LEGEND_CHAR_PX_WIDTH,
+                            y: line_top + LEGEND_LINE_PX_HEIGHT / 2,
+                            align: Align::Left,
+                            size: theme.font.small,
+                        },
+                        badge,
+                    }
+                })
+            })
+            .collect(),
+    });
+
+    Layout {
+        width: fig_width,
+        height: fig_height,
+        title: fit_line(
+            &format!("Anchor Program: {}", idl.name),
+            fig_width / 2,
+            theme.sizes.header / 4,
+            Align::Center,
+            &theme.font.family,
+            theme.font.title,
+            fig_width - 2 * theme.sizes.spacing,
+        ),
+        version: fit_line(
+            &match &program.diff {
+                Some(diff) => format!("Version: {} → {}", diff.old_version, diff.new_version),
+                None => format!("Version: {}", idl.version),
+            },
+            fig_width / 2,
+            theme.sizes.header / 2,
+            Align::Center,
+            &theme.font.family,
+            theme.font.title,
+            fig_width - 2 * theme.sizes.spacing,
+        ),
+        roles: role_entries,
+        statuses: status_entries,
+        separators,
+        columns: layout_columns,
+        types,
+        legend,
+        theme: theme.clone(),
+    }
+}
+
+/// Roles of the boxes in the figure of `idl`, in `Role::ALL` order: those the color legend lists.
+pub fn roles_present(idl: &Idl) -> Vec<Role> {
+    let instructions: Vec<&IdlInstruction> = idl
+        .instructions
+        .iter()
+        .chain(idl.state.iter().flat_map(|state| state.methods.iter()))
+        .collect();
+    let accounts: Vec<IdlAccount> = instructions
+        .iter()
+        .flat_map(|instruction| {
+            unpack_group(IdlAccounts {
+                name: "".to_string(),
+                accounts: instruction.accounts.clone(),
+            })
+        })
+        .collect();
+    Role::ALL
+        .iter()
+        .copied()
+        .filter(|role| match role {
+            Role::Instruction => !instructions.is_empty(),
+            Role::Signer => accounts.iter().any(|account| account.is_signer),
+            Role::Mut => accounts.iter().any(|account| account.is_mut),
+            Role::Immut => accounts.iter().any(|account| !account.is_mut),
+            Role::Arg => instructions
+                .iter()
+                .any(|instruction| !instruction.args.is_empty()),
+        })
+        .collect()
+}
+
+/// Lays out the entries of a legend of box colors with `descriptions` in rows that fit
+/// `fig_width`, as (entry index, entry width) pairs: a swatch, then the description.
+fn color_legend_rows(
+    descriptions: &[&str],
+    fig_width: usize,
+    theme: &Theme,
+) -> Vec<Vec<(usize, usize)>> {
+    let max_width = fig_width.saturating_sub(2 * theme.sizes.spacing);
+    let mut rows: Vec<Vec<(usize, usize)>> = vec![];
+    let mut row_width = 0;
+    for (i, description) in descriptions.iter().enumerate() {
+        let text_width = text_px_width(description, &theme.font.family, theme.font.small);
+        let entry_width = ROLE_SWATCH_PX_WIDTH + theme.sizes.spacing / 2 + text_width;
+        if rows.is_empty() || row_width + ROLE_ENTRY_GAP + entry_width > max_width {
+            rows.push(vec![]);
+            row_width = 0;
+        } else {
+            row_width += ROLE_ENTRY_GAP;
+        }
+        if let Some(row) = rows.last_mut() {
+            row.push((i, entry_width));
+        }
+        row_width += entry_width;
+    }
+    rows
+}
+
+/// Swatches and description lines of the color legend entries of `rows` (see
+/// `color_legend_rows`) with `descriptions`, by entry index, each row centered in the figure,
+/// from `top` down.
+fn layout_color_legend(
+    rows: &[Vec<(usize, usize)>],
+    descriptions: &[&str],
+    top: usize,
+    fig_width: usize,
+    theme: &Theme,
+) -> Vec<(usize, Rect, Line)> {
+    let mut entries = vec![];
+    for (r, row) in rows.iter().enumerate() {
+        let row_width = row.iter().map(|(_, width)| width).sum::<usize>()
+            + ROLE_ENTRY_GAP * row.len().saturating_sub(1);
+        let mut left = fig_width.saturating_sub(row_width) / 2;
+        let center_y = top + ROLE_LINE_PX_HEIGHT * r + ROLE_LINE_PX_HEIGHT / 2;
+        for (i, entry_width) in row {
+            let swatch = Rect {
+                left,
+                top: center_y - ROLE_SWATCH_PX_WIDTH / 2,
+                width: ROLE_SWATCH_PX_WIDTH,
+                height: ROLE_SWATCH_PX_WIDTH,
+            };
+            let description = Line {
+                text: descriptions[*i].to_string(),
+                x: swatch.right() + theme.sizes.spacing / 2,
+                y: center_y,
+                align: Align::Left,
+                size: theme.font.small,
+            };
+            entries.push((*i, swatch, description));
+            left += entry_width + ROLE_ENTRY_GAP;
+        }
+    }
+    entries
+}
+
+/// Horizontal distance between the left edges of two neighbouring columns.
+fn column_stride(width: usize, theme: &Theme) -> usize {
+    theme.sizes.box_width * width + theme.sizes.separator + (1 + width) * theme.sizes.spacing
+}
+
+/// Number of rows `count` boxes take, `width` to a row.
+fn rows(count: usize, width: usize) -> usize {
+    if count % width == 0 {
+        count / width
+    } else {
+        count / width + 1
+    }
+}
+
+/// Pixel height of a column below its instruction box: signers (at least one row), accounts, args.
+fn column_section_height(
+    instruction: &IdlInstruction,
+    info: &AccountsInfo,
+    width: usize,
+    theme: &Theme,
+    show_seeds: bool,
+) -> usize {
+    let signers = unpack_group(IdlAccounts {
+        name: "".to_string(),
+        accounts: instruction.accounts.clone(),
+    })
+    .iter()
+    .filter(|account| account.is_signer)
+    .count();
+    let sign_height = rows(signers, width).max(1);
+    let arg_height = rows(instruction.args.len(), width);
+    (arg_height + sign_height) * (theme.sizes.box_height + theme.sizes.spacing)
+        + section_height(&instruction.accounts, info, width, theme, show_seeds)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn layout_column(
+    i: usize,
+    label: &str,
+    name: &str,
+    instruction: &IdlInstruction,
+    info: &AccountsInfo,
+    docs: &InstructionDocs,
+    width: usize,
+    theme: &Theme,
+    show_seeds: bool,
+) -> Column {
+    let column_left = column_stride(width, theme) * i;
+    let inner_width = theme.sizes.box_width * width + theme.sizes.spacing * (width + 1);
+    // the box at (row l, position k) of a grid starting at `top`
+    let grid_rect = |top: usize, l: usize, k: usize| Rect {
+        left: column_left + theme.sizes.spacing * (k + 1) + theme.sizes.box_width * k,
+        top: top + theme.sizes.spacing * (1 + l) + theme.sizes.box_height * l,
+        width: theme.sizes.box_width,
+        height: theme.sizes.box_height,
+    };
+
+    let mut header = LayoutBox::new(
+        Role::Instruction,
+        label,
+        name,
+        Rect {
+            left: column_left + inner_width / 2 - theme.sizes.box_width / 2,
+            top: theme.sizes.header + theme.sizes.spacing,
+            width: theme.sizes.box_width,
+            height: theme.sizes.box_height,
+        },
+        theme,
+    );
+    header.doc = docs.doc.clone();
+
+    // Signers of the instruction and of its groups, flattened
+    let signers_top = theme.sizes.header + 2 * theme.sizes.spacing + theme.sizes.box_height;
+    let signers: Vec<LayoutBox> = diff::account_paths(&instruction.accounts)
+        .into_iter()
+        .filter(|(_, account)| account.is_signer)
+        .enumerate()
+        .map(|(s, (path, signer))| {
+            let rect = grid_rect(signers_top, s / width, s % width);
+            let mut signer_box =
+                LayoutBox::new(Role::Signer, "Signer:", signer.name.clone(), rect, theme);
+            signer_box.path = path;
+            signer_box.doc = info
+                .find_field(&signer.name)
+                .and_then(|field| field.doc.clone());
+            signer_box
+        })
+        .collect();
+
+    // Accounts (and account groups, recursively)
+    let accounts_top = signers_top
+        + (theme.sizes.spacing + theme.sizes.box_height) * rows(signers.len(), width).max(1);
+    let mut accounts = vec![];
+    let mut groups = vec![];
+    layout_accounts(
+        &instruction.accounts,
+        info,
+        column_left,
+        width,
+        theme,
+        accounts_top,
+        0,
+        show_seeds,
+        &mut accounts,
+        &mut groups,
+    );
+
+    // Args
+    let args_top =
+        accounts_top + section_height(&instruction.accounts, info, width, theme, show_seeds);
+    let args = instruction
+        .args
+        .iter()
+        .enumerate()
+        .map(|(a, arg)| {
+            let rect = grid_rect(args_top, a / width, a % width);
+            // the type is the label, abbreviated if it wouldn't fit even when shrunk
+            let char_width =
+                text_px_width("M", &theme.font.family, min_text_size(theme.font.text)).max(1);
+            let inner_width = theme.sizes.box_width.saturating_sub(2 * theme.sizes.spacing);
+            let max_chars = (inner_width / char_width).saturating_sub(1).max(1);
+            let label = types::abbreviated_type_label(&arg.ty, max_chars);
+            let mut arg_box = LayoutBox::new(
+                Role::Arg,
+                format!("{}:", label),
+                arg.name.clone(),
+                rect,
+                theme,
+            );
+            arg_box.doc = docs.args.get(&arg.name).cloned();
+            arg_box
+        })
+        .collect();
+
+    Column {
+        rect: Rect {
+            left: column_left,
+            top: theme.sizes.header + theme.sizes.spacing,
+            width: inner_width,
+            height: 0,
+        },
+        instruction: header,
+        signers,
+        accounts,
+        groups,
+        args,
+        footnotes: vec![],
+    }
+}
+
+/// Sets how each box of `column` changed, from the diff of its instruction.
+fn mark_diff(column: &mut Column, diff: &InstructionDiff) {
+    column.instruction.diff = Some(ItemDiff {
+        name: diff.name.clone(),
+        status: diff.status,
+        changes: vec![],
+    });
+    for account in column.signers.iter_mut().chain(column.accounts.iter_mut()) {
+        account.diff = diff.account(&account.path).cloned();
+    }
+    for arg in column.args.iter_mut() {
+        arg.diff = diff.arg(&arg.name).cloned();
+    }
+}
+
+/// Adds a lint finding to the box of `column` it is about, marking the box with a warning badge
+/// if there's room for one.
+fn mark_lint(column: &mut Column, finding: &Finding) {
+    let layout_box = match &finding.account {
+        Some(path) => {
+            let mut accounts = column.signers.iter_mut().chain(column.accounts.iter_mut());
+            match accounts.find(|b| b.path == *path) {
+                Some(layout_box) => layout_box,
+                None => return,
+            }
+        }
+        None => &mut column.instruction,
+    };
+    layout_box.lints.push(finding.message.clone());
+    let text = "WARNING";
+    let right = match &layout_box.pda {
+        Some(pda) => pda.badge.left.saturating_sub(BADGE_GAP),
+        None => layout_box.rect.right() - 1,
+    };
+    let width = badge_px_width(text);
+    if right < layout_box.rect.left + width {
+        return;
+    }
+    layout_box.warning = Some(Badge {
+        text: text.to_string(),
+        rect: Rect {
+            left: right - width,
+            top: layout_box.rect.top + 1,
+            width,
+            height: BADGE_PX_HEIGHT,
+        },
+    });
+}
+
+/// Numbers the boxes of `column` that have a note (see `LayoutBox::note`), in order, marking
+/// their labels, and lists their notes as footnotes from `top` down. Returns the number of
+/// footnote lines.
+fn layout_footnotes(column: &mut Column, top: usize, theme: &Theme) -> usize {
+    let max_width = column.rect.width.saturating_sub(2 * theme.sizes.spacing);
+    let boxes = std::iter::once(&mut column.instruction)
+        .chain(column.signers.iter_mut())
+        .chain(column.accounts.iter_mut())
+        .chain(column.args.iter_mut());
+    let mut lines = vec![];
+    let mut number = 0;
+    for layout_box in boxes {
+        let note = match layout_box.note() {
+            Some(note) => note,
+            None => continue,
+        };
+        number += 1;
+        layout_box.set_footnote(number, theme);
+        let text = format!("[{}] {}: {}", number, layout_box.name, note);
+        lines.extend(wrap_text(
+            &text,
+            max_width,
+            &theme.font.family,
+            theme.font.small,
+        ));
+    }
+    column.footnotes = lines
+        .into_iter()
+        .enumerate()
+        .map(|(l, text)| Line {
+            text,
+            x: column.rect.left + theme.sizes.spacing,
+            y: top + FOOTNOTE_LINE_PX_HEIGHT * l + FOOTNOTE_LINE_PX_HEIGHT / 2,
+            align: Align::Left,
+            size: theme.font.small,
+        })
+        .collect();
+    column.footnotes.len()
+}
+
+/// Lays out the cards of `type_defs` in rows across the figure, in a panel starting at `top`.
+fn layout_types(
+    type_defs: &[TypeDef],
+    top: usize,
+    fig_width: usize,
+    theme: &Theme,
+) -> Option<TypesPanel> {
+    if type_defs.is_empty() {
+        return None;
+    }
+    let card_width = (2 * theme.sizes.box_width + theme.sizes.spacing)
+        .min(fig_width.saturating_sub(2 * theme.sizes.spacing));
+    let per_row =
+        (fig_width.saturating_sub(theme.sizes.spacing) / (card_width + theme.sizes.spacing)).max(1);
+    let cards_top = top + theme.sizes.separator + TYPES_LABEL_PX_HEIGHT;
+
+    let mut cards = vec![];
+    let mut row_top = cards_top;
+    for row in type_defs.chunks(per_row) {
+        let texts: Vec<Vec<String>> = row
+            .iter()
+            .map(|def| type_lines(def, card_width, theme))
+            .collect();
+        let row_height = texts.iter().map(Vec::len).max().unwrap_or(0) * SEED_LINE_PX_HEIGHT
+            + theme.sizes.box_height
+            + theme.sizes.spacing;
+        for (k, (def, texts)) in row.iter().zip(texts).enumerate() {
+            let rect = Rect {
+                left: theme.sizes.spacing + (card_width + theme.sizes.spacing) * k,
+                top: row_top,
+                width: card_width,
+                height: row_height,
+            };
+            let lines = texts
+                .iter()
+                .enumerate()
+                .map(|(l, text)| {
+                    fit_line(
+                        text,
+                        rect.left + theme.sizes.spacing,
+                        rect.top
+                            + theme.sizes.box_height
+                            + SEED_LINE_PX_HEIGHT * l
+                            + SEED_LINE_PX_HEIGHT / 2
+                            - theme.sizes.spacing / 2,
+                        Align::Left,
+                        &theme.font.family,
+                        theme.font.small,
+                        card_width - 2 * theme.sizes.spacing,
+                    )
+                })
+                .collect();
+            let (label_lines, name_lines) = box_text(def.kind.label(), &def.name, rect, theme);
+            cards.push(TypeCard {
+                kind: def.kind,
+                label: def.kind.label().to_string(),
+                name: def.name.clone(),
+                rect,
+                label_lines,
+                name_lines,
+                lines,
+            });
+        }
+        row_top += row_height + theme.sizes.spacing;
+    }
+
+    Some(TypesPanel {
+        rect: Rect {
+            left: 0,
+            top,
+            width: fig_width,
+            height: row_top - top,
+        },
+        separator: Rect {
+            left: 0,
+            top,
+            width: fig_width,
+            height: theme.sizes.separator,
+        },
+        label: Line {
+            text: "Types:".to_string(),
+            x: theme.sizes.spacing,
+            y: top + theme.sizes.separator + TYPES_LABEL_PX_HEIGHT / 2,
+            align: Align::Left,
+            size: theme.font.text,
+        },
+        cards,
+    })
+}
+
+/// Text lines of a type card `card_width` wide: its size, then its fields (or variants and
+/// their fields), then where it's used, as many to a line as fit.
+fn type_lines(def: &TypeDef, card_width: usize, theme: &Theme) -> Vec<String> {
+    let mut lines = vec![match def.kind {
+        TypeKind::Account => {
+            let data = BorshSize {
+                bytes: def.size.bytes - DISCRIMINATOR_SIZE,
+                variable: def.size.variable,
+            };
+            format!(
+                "size: {} ({} discriminator + {})",
+                def.size, DISCRIMINATOR_SIZE, data
+            )
+        }
+        TypeKind::Struct | TypeKind::Enum => format!("size: {}", def.size),
+    }];
+    let field_line = |indent: &str, field: &TypeField| {
+        let ty = types::type_label(&field.ty);
+        format!("{}{}: {} ({})", indent, field.name, ty, field.size)
+    };
+    lines.extend(def.fields.iter().map(|field| field_line("", field)));
+    for variant in &def.variants {
+        lines.push(format!("- {}", variant.name));
+        lines.extend(variant.fields.iter().map(|field| field_line("    ", field)));
+    }
+    let max_width = card_width - 2 * theme.sizes.spacing;
+    let mut used_by = "used by:".to_string();
+    for (i, path) in def.used_by.iter().enumerate() {
+        let separator = if i + 1 < def.used_by.len() { "," } else { "" };
+        let next = format!(" {}{}", path, separator);
+        if text_px_width(
+            &(used_by.clone() + &next),
+            &theme.font.family,
+            theme.font.small,
+        ) > max_width
+        {
+            lines.push(std::mem::replace(&mut used_by, " ".to_string()));
+        }
+        used_by += &next;
+    }
+    if !def.used_by.is_empty() {
+        lines.push(used_by);
+    }
+    lines
+}
+
+/// Lays out the accounts in `items` in the column at `column_left`, starting at pixel height `top`.
+/// Mutable accounts come first, then immutable accounts, then each composite account group
+/// (e.g. a shared `#[derive(Accounts)]` struct embedded in another) as a labeled, bordered region
+/// which is populated recursively.
+///
+/// Below its name, each account box holds its declared type and constraint badges (from `info`)
+/// and, for PDAs with `show_seeds` set, its seeds.
+#[allow(clippy::too_many_arguments)]
+fn layout_accounts(
+    items: &[IdlAccountItem],
+    info: &AccountsInfo,
+    column_left: usize,
+    width: usize,
+    theme: &Theme,
+    top: usize,
+    depth: usize,
+    show_seeds: bool,
+    boxes: &mut Vec<LayoutBox>,
+    groups: &mut Vec<Group>,
+) {
+    let column_right =
+        column_left + theme.sizes.box_width * width + theme.sizes.spacing * (1 + width);
+
+    let accounts = ordered_accounts(items);
+    let mut row_top = top;
+    for (row, row_height) in accounts
+        .chunks(width)
+        .zip(row_heights(&accounts, info, width, theme, show_seeds))
+    {
+        for (k, account) in row.iter().enumerate() {
+            let rect = Rect {
+                left: column_left + theme.sizes.spacing * (k + 1) + theme.sizes.box_width * k,
+                top: row_top + theme.sizes.spacing,
+                width: theme.sizes.box_width,
+                height: row_height,
+            };
+            let (label, role) = if account.is_mut {
+                ("Mutable Account:", Role::Mut)
+            } else {
+                ("Immutable Account:", Role::Immut)
+            };
+            let mut account_box = LayoutBox::new(role, label, account.name.clone(), rect, theme);
+
+            // below the name: declared type, constraint badges, then seeds
+            let field = info.field(&account.name);
+            let mut extras_top = rect.top + theme.sizes.box_height;
+            if let Some(ty) = field.and_then(|field| field.ty.as_ref()) {
+                account_box.ty = Some(fit_line(
+                    ty,
+                    rect.center_x(),
+                    extras_top + TYPE_LINE_PX_HEIGHT / 2 - theme.sizes.spacing / 2,
+                    Align::Center,
+                    &theme.font.family,
+                    theme.font.small,
+                    rect.width - 2 * theme.sizes.spacing,
+                ));
+                extras_top += TYPE_LINE_PX_HEIGHT;
+            }
+            account_box.unchecked = field.map(|field| field.is_unchecked()).unwrap_or(false);
+            account_box.doc = field.and_then(|field| field.doc.clone());
+
+            let badges = field.map(|field| field.badges()).unwrap_or_default();
+            let badge_rows = badge_rows(&badges, theme);
+            for (r, badge_row) in badge_rows.iter().enumerate() {
+                for (offset, badge) in badge_row {
+                    account_box.badges.push(Badge {
+                        text: badge.to_string(),
+                        rect: Rect {
+                            left: rect.left + theme.sizes.spacing + offset,
+                            top: extras_top + BADGE_LINE_PX_HEIGHT * r,
+                            width: badge_px_width(badge),
+                            height: BADGE_PX_HEIGHT,
+                        },
+                    });
+                }
+            }
+            extras_top += BADGE_LINE_PX_HEIGHT * badge_rows.len();
+
+            account_box.pda = account
+                .pda
+                .as_ref()
+                .map(|pda| layout_pda(pda, rect, extras_top, show_seeds, theme));
+            boxes.push(account_box);
+        }
+        row_top += theme.sizes.spacing + row_height;
+    }
+
+    // Groups go below this level's own accounts, each with a border inset by its depth
+    let inset = GROUP_INSET * (depth + 1).min((theme.sizes.spacing - 1) / GROUP_INSET);
+    let mut group_top = row_top;
+    for item in items {
+        if let IdlAccountItem::IdlAccounts(group) = item {
+            group_top += theme.sizes.spacing;
+            let group_info = info.group(&group.name);
+            let group_height = GROUP_LABEL_PX_HEIGHT
+                + section_height(&group.accounts, group_info, width, theme, show_seeds)
+                + theme.sizes.spacing;
+            let rect = Rect {
+                left: column_left + inset,
+                top: group_top,
+                width: column_right - inset - (column_left + inset),
+                height: group_height,
+            };
+            groups.push(Group {
+                name: group.name.clone(),
+                rect,
+                label: fit_line(
+                    &group.name,
+                    rect.left + theme.sizes.spacing,
+                    rect.top + GROUP_LABEL_PX_HEIGHT / 2,
+                    Align::Left,
+                    &theme.font.family,
+                    theme.font.text,
+                    rect.width - 2 * theme.sizes.spacing,
+                ),
+                depth,
+            });
+            let first = boxes.len();
+            layout_accounts(
+                &group.accounts,
+                group_info,
+                column_left,
+                width,
+                theme,
+                group_top + GROUP_LABEL_PX_HEIGHT,
+                depth + 1,
+                show_seeds,
+                boxes,
+                groups,
+            );
+            for account_box in &mut boxes[first..] {
+                account_box.path = format!("{}.{}", group.name, account_box.path);
+            }
+            group_top += group_height;
+        }
+    }
+}
+
+/// Derived-address badge in the top right corner of the account box `rect` and,
+/// if `show_seeds` is set, the seeds from `seeds_top` down.
+fn layout_pda(pda: &IdlPda, rect: Rect, seeds_top: usize, show_seeds: bool, theme: &Theme) -> Pda {
+    let seeds = if show_seeds {
+        pda.seeds
+            .iter()
+            .enumerate()
+            .map(|(s, seed)| {
+                fit_line(
+                    &format!("- {}", seed_label(seed)),
+                    rect.left + theme.sizes.spacing,
+                    seeds_top + SEED_LINE_PX_HEIGHT * s + SEED_LINE_PX_HEIGHT / 2,
+                    Align::Left,
+                    &theme.font.family,
+                    theme.font.small,
+                    rect.width - 2 * theme.sizes.spacing,
+                )
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    Pda {
+        badge: Rect {
+            left: rect.right() - PDA_BADGE_PX_WIDTH - 1,
+            top: rect.top + 1,
+            width: PDA_BADGE_PX_WIDTH,
+            height: BADGE_PX_HEIGHT,
+        },
+        seeds,
+    }
+}
+
+/// Human readable form of a PDA seed. Constant byte seeds are shown as a byte string
+/// when they are printable UTF-8, and as raw bytes otherwise.
+fn seed_label(seed: &IdlSeed) -> String {
+    match seed {
+        IdlSeed::Const(seed) => match &seed.value {
+            serde_json::Value::String(value) => format!("{:?}", value),
+            serde_json::Value::Array(values) => {
+                let bytes: Option<Vec<u8>> = values
+                    .iter()
+                    .map(|value| value.as_u64().and_then(|byte| byte.try_into().ok()))
+                    .collect();
+                match bytes.as_ref().map(|bytes| std::str::from_utf8(bytes)) {
+                    Some(Ok(text)) if !text.chars().any(char::is_control) => {
+                        format!("b{:?}", text)
+                    }
+                    _ => seed.value.to_string(),
+                }
+            }
+            value => value.to_string(),
+        },
+        IdlSeed::Arg(seed) => format!("arg: {}", seed.path),
+        IdlSeed::Account(seed) => format!("account: {}", seed.path),
+    }
+}
+
+/// Accounts directly in `items` (not in groups), in drawing order: mutable accounts first.
+fn ordered_accounts(items: &[IdlAccountItem]) -> Vec<&IdlAccount> {
+    let accounts: Vec<&IdlAccount> = items
+        .iter()
+        .filter_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => Some(account),
+            IdlAccountItem::IdlAccounts(_) => None,
+        })
+        .collect();
+    let mut_accounts = accounts.iter().filter(|account| account.is_mut);
+    let immut_accounts = accounts.iter().filter(|account| !account.is_mut);
+    mut_accounts.chain(immut_accounts).copied().collect()
+}
+
+/// Pixel height of the boxes in each row of `accounts`. Rows are stretched to fit the
+/// declared type, constraint badges and (for a PDA with listed seeds) the seed list of
+/// their tallest box.
+fn row_heights(
+    accounts: &[&IdlAccount],
+    info: &AccountsInfo,
+    width: usize,
+    theme: &Theme,
+    show_seeds: bool,
+) -> Vec<usize> {
+    accounts
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|account| {
+                    let field = info.field(&account.name);
+                    let ty = field.and_then(|field| field.ty.as_ref()).map_or(0, |_| 1);
+                    let badges = field.map_or(0, |field| badge_rows(&field.badges(), theme).len());
+                    let seeds = match &account.pda {
+                        Some(pda) if show_seeds => pda.seeds.len(),
+                        _ => 0,
+                    };
+                    ty * TYPE_LINE_PX_HEIGHT
+                        + badges * BADGE_LINE_PX_HEIGHT
+                        + seeds * SEED_LINE_PX_HEIGHT
+                })
+                .max()
+                .unwrap_or(0)
+                + theme.sizes.box_height
+        })
+        .collect()
+}
+
+/// Pixel height of the accounts in `items` as laid out by `layout_accounts`:
+/// rows of this level's accounts followed by each (recursively sized) account group.
+fn section_height(
+    items: &[IdlAccountItem],
+    info: &AccountsInfo,
+    width: usize,
+    theme: &Theme,
+    show_seeds: bool,
+) -> usize {
+    let accounts = ordered_accounts(items);
+    let accounts_height: usize = row_heights(&accounts, info, width, theme, show_seeds)
+        .iter()
+        .map(|row_height| theme.sizes.spacing + row_height)
+        .sum();
+    let groups_height: usize = items
+        .iter()
+        .map(|item| match item {
+            IdlAccountItem::IdlAccount(_) => 0,
+            IdlAccountItem::IdlAccounts(group) => {
+                let group_info = info.group(&group.name);
+                theme.sizes.spacing
+                    + GROUP_LABEL_PX_HEIGHT
+                    + section_height(&group.accounts, group_info, width, theme, show_seeds)
+                    + theme.sizes.spacing
+            }
+        })
+        .sum();
+    accounts_height + groups_height
+}
+
+/// Lays out constraint `badges` in rows that fit an account box of the theme's box width,
+/// as (left offset, badge) pairs.
+fn badge_rows<'a>(badges: &'a [String], theme: &Theme) -> Vec<Vec<(usize, &'a str)>> {
+    let mut rows: Vec<Vec<(usize, &str)>> = vec![];
+    let mut offset = 0;
+    for badge in badges {
+        let badge_width = badge_px_width(badge);
+        if rows.is_empty() || offset + badge_width > theme.sizes.box_width - 2 * theme.sizes.spacing
+        {
+            rows.push(vec![]);
+            offset = 0;
+        }
+        if let Some(row) = rows.last_mut() {
+            row.push((offset, badge.as_str()));
+        }
+        offset += badge_width + BADGE_GAP;
+    }
+    rows
+}
+
+/// Pixel width of a badge reading `text`.
+pub fn badge_px_width(text: &str) -> usize {
+    text.chars().count() * BADGE_CHAR_PX_WIDTH + 4
+}
+
+/// Pixel width of `text` in the monospace font at `size`, as measured by the font.
+pub fn text_px_width(text: &str, family: &str, size: u32) -> usize {
+    FontDesc::new(FontFamily::from(family), size as f64, FontStyle::Normal)
+        .box_size(text)
+        .map(|(width, _)| width as usize)
+        // without the font, go by the usual advance of monospace fonts, 0.6 of their size
+        .unwrap_or_else(|_| text.chars().count() * size as usize * 3 / 5)
+}
+
+/// Smallest size that text of font size `size` is shrunk to before it's wrapped.
+fn min_text_size(size: u32) -> u32 {
+    size * 3 / 4
+}
+
+/// Fits `text` into at most `max_lines` lines of at most `max_width` pixels, returning their
+/// font size and text. The text is first shrunk from `size` (see `min_text_size`) to fit on
+/// one line, then wrapped after underscores and at spaces, breaking words only if they alone
+/// are too long, and as a last resort its last line is cut short with an ellipsis.
+pub fn fit_text(
+    text: &str,
+    max_width: usize,
+    family: &str,
+    size: u32,
+    max_lines: usize,
+) -> (u32, Vec<String>) {
+    let min_size = min_text_size(size);
+    for size in (min_size..=size).rev() {
+        if text_px_width(text, family, size) <= max_width {
+            return (size, vec![text.to_string()]);
+        }
+    }
+
+    let mut lines = wrap_text(text, max_width, family, min_size);
+    if lines.len() > max_lines {
+        lines.truncate(max_lines.max(1));
+        if let Some(last) = lines.last_mut() {
+            let mut cut: String = last.clone();
+            while !cut.is_empty()
+                && text_px_width(&format!("{}…", cut), family, min_size) > max_width
+            {
+                cut.pop();
+            }
+            *last = format!("{}…", cut);
+        }
+    }
+    (min_size, lines)
+}
+
+/// Greedily wraps `text` into lines of at most `max_width` pixels at font size `size`,
+/// breaking after underscores and at spaces where possible.
+fn wrap_text(text: &str, max_width: usize, family: &str, size: u32) -> Vec<String> {
+    let fits = |line: &str| text_px_width(line.trim_end(), family, size) <= max_width;
+    let mut lines: Vec<String> = vec![];
+    let mut line = String::new();
+    for word in text.split_inclusive(['_', ' ']) {
+        if line.is_empty() || fits(&format!("{}{}", line, word)) {
+            line.push_str(word);
+            continue;
+        }
+        lines.push(line.trim_end().to_string());
+        line = word.to_string();
+    }
+    lines.push(line.trim_end().to_string());
+
+    // words too long for a line of their own are broken anywhere
+    let mut broken = vec![];
+    for line in lines {
+        if fits(&line) {
+            broken.push(line);
+            continue;
+        }
+        let mut piece = String::new();
+        for c in line.chars() {
+            if !piece.is_empty() && !fits(&format!("{}{}", piece, c)) {
+                broken.push(std::mem::take(&mut piece));
+            }
+            piece.push(c);
+        }
+        broken.push(piece);
+    }
+    broken
+}
+
+/// A single `line` of text at `size` or smaller, fitted to `max_width` (see `fit_text`).
+fn fit_line(
+    text: &str,
+    x: usize,
+    y: usize,
+    align: Align,
+    family: &str,
+    size: u32,
+    max_width: usize,
+) -> Line {
+    let (size, lines) = fit_text(text, max_width, family, size, 1);
+    Line {
+        text: lines.into_iter().next().unwrap_or_default(),
+        x,
+        y,
+        align,
+        size,
+    }
+}
+
+/// Label (one line) and name (up to two lines) of a box at `rect`, fitted to its width and
+/// centered in its top `box_height`: at a third and two thirds of it when both fit on one line.
+fn box_text(label: &str, name: &str, rect: Rect, theme: &Theme) -> (Vec<Line>, Vec<Line>) {
+    let max_width = rect.width.saturating_sub(2 * theme.sizes.spacing);
+    let (label_size, label) = fit_text(label, max_width, &theme.font.family, theme.font.text, 1);
+    let (name_size, name) = fit_text(name, max_width, &theme.font.family, theme.font.text, 2);
+    let count = label.len() + name.len();
+    let line_height = if count <= 2 {
+        theme.sizes.box_height / 3
+    } else {
+        SEED_LINE_PX_HEIGHT
+    };
+    let first_y = rect.top + theme.sizes.box_height / 2 - line_height * (count - 1) / 2;
+    let line = |(i, text): (usize, String), size: u32| Line {
+        text,
+        x: rect.center_x(),
+        y: first_y + line_height * i,
+        align: Align::Center,
+        size,
+    };
+    let label_count = label.len();
+    (
+        label
+            .into_iter()
+            .enumerate()
+            .map(|l| line(l, label_size))
+            .collect(),
+        name.into_iter()
+            .enumerate()
+            .map(|(i, text)| line((label_count + i, text), name_size))
+            .collect(),
+    )
+}
+
+/// Pixel width of the widest box text of an instruction column at its usual size:
+/// the instruction name, account and group names, account types and arg types and names.
+fn widest_text(
+    name: &str,
+    instruction: &IdlInstruction,
+    info: &AccountsInfo,
+    theme: &Theme,
+) -> usize {
+    let mut widest = text_px_width(name, &theme.font.family, theme.font.text);
+    let accounts = unpack_group(IdlAccounts {
+        name: "".to_string(),
+        accounts: instruction.accounts.clone(),
+    });
+    for account in &accounts {
+        widest = widest.max(text_px_width(
+            &account.name,
+            &theme.font.family,
+            theme.font.text,
+        ));
+    }
+    for arg in &instruction.args {
+        let label = format!("{}:", types::type_label(&arg.ty));
+        widest = widest
+            .max(text_px_width(&label, &theme.font.family, theme.font.text))
+            .max(text_px_width(
+                &arg.name,
+                &theme.font.family,
+                theme.font.text,
+            ));
+    }
+    widest.max(widest_info_text(info, theme))
+}
+
+/// Pixel width of the widest account type and group name in `info`, at their usual size.
+fn widest_info_text(info: &AccountsInfo, theme: &Theme) -> usize {
+    let types = info
+        .fields
+        .values()
+        .filter_map(|field| field.ty.as_ref())
+        .map(|ty| text_px_width(ty, &theme.font.family, theme.font.small));
+    let groups = info.groups.iter().map(|(name, group)| {
+        text_px_width(name, &theme.font.family, theme.font.text).max(widest_info_text(group, theme))
+    });
+    types.chain(groups).max().unwrap_or(0)
+}
+
+/// Legend entries (badge code, description) of the constraints drawn in the figure,
+/// laid out in rows that fit `fig_width`, as (left offset, code, description) triples.
+fn legend_rows(
+    source: &SourceInfo,
+    fig_width: usize,
+    theme: &Theme,
+) -> Vec<Vec<(usize, &'static str, &'static str)>> {
+    let codes = source.badge_codes();
+    let mut rows: Vec<Vec<(usize, &str, &str)>> = vec![];
+    // the first row starts after the "Constraints:" label
+    let mut offset = 13 * LEGEND_CHAR_PX_WIDTH;
+    for (code, _, description) in BADGES.iter().filter(|(code, _, _)| codes.contains(*code)) {
+        let entry_width = badge_px_width(code) + (1 + description.len()) * LEGEND_CHAR_PX_WIDTH;
+        if rows.is_empty()
+            || offset + entry_width > fig_width.saturating_sub(2 * theme.sizes.spacing)
+        {
+            if !rows.is_empty() {
+                offset = 0;
+            }
+            rows.push(vec![]);
+        }
+        if let Some(row) = rows.last_mut() {
+            row.push((offset, *code, *description));
+        }
+        offset += entry_width + 2 * LEGEND_CHAR_PX_WIDTH;
+    }
+    rows
+}
+
+/// Takes any nested `account_group` structure, flattens it, and
+/// returns all accounts within as a Vec<IdlAccounts>.
+fn unpack_group(account_group: IdlAccounts) -> Vec<IdlAccount> {
+    let mut v: Vec<IdlAccount> = vec![];
+
+    for account in account_group.accounts.iter() {
+        match account {
+            IdlAccountItem::IdlAccount(idl_account) => v.push(idl_account.clone()),
+            IdlAccountItem::IdlAccounts(idl_accounts) => {
+                v.append(&mut unpack_group(idl_accounts.clone()))
+            }
+        };
+    }
+    v
+}
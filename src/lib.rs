@@ -0,0 +1,53 @@
+//! Visualizations of the instructions of anchor programs, as used by the `anchor-viz` binary.
+//!
+//! A visualization is made in two steps: load a `viz::Program` (from the program source, an idl
+//! json or by finding the programs of a workspace), then `viz::render` it as
+//! `viz::RenderOptions` say: in one of the `viz::Format`s, in a `theme::Theme`, and so on:
+//!
+//! ```no_run
+//! use anchor_viz::theme::Theme;
+//! use anchor_viz::viz::{self, Format, Program, RenderOptions};
+//! use std::path::Path;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let lib = viz::find_program(Path::new("."), Some("my_program"))?;
+//! let program = Program::from_source(&lib, false)?;
+//! let options = RenderOptions {
+//!     theme: Theme::load("dark")?,
+//!     footnotes: true,
+//!     ..RenderOptions::new(Format::Svg)
+//! };
+//! viz::render(&program, Path::new("my_program.svg"), &options.clone().with_width(3)?)?;
+//!
+//! let options = RenderOptions { format: Format::Html, ..options };
+//! for dir in viz::workspace_programs(Path::new("."))? {
+//!     let program = Program::from_source(&dir.join("src/lib.rs"), false)?;
+//!     let out = Path::new(&program.idl.name).with_extension("html");
+//!     viz::render(&program, &out, &options)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `layout::layout` computes the pixel layout that the png and svg are drawn from, for renderers
+//! of your own, and `types::type_defs` the account structs and custom types with their sizes.
+//! `diff::merged` merges two versions of a program into one that renders as their diff, and
+//! `compat::check` tells which of their changes break clients. `matrix::account_matrix` tells
+//! which instructions use which accounts, and `lint::lint` finds common Anchor pitfalls.
+//!
+//! Errors are `anyhow::Error`s which wrap an `error::VizError` where possible.
+
+pub mod compat;
+pub mod diff;
+pub mod dot;
+pub mod error;
+pub mod git;
+pub mod html;
+pub mod layout;
+pub mod lint;
+pub mod matrix;
+pub mod mermaid;
+pub mod source;
+pub mod theme;
+pub mod types;
+pub mod viz;
@@ -0,0 +1,242 @@
+use crate::source::{AccountsInfo, FieldInfo, Location};
+use crate::viz::Program;
+use anchor_syn::idl::{IdlAccount, IdlAccountItem};
+use heck::MixedCase;
+use serde::Serialize;
+use std::fmt;
+
+/// A common Anchor pitfall that `lint` looks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// An `UncheckedAccount` or `AccountInfo` without a `/// CHECK:` comment saying why
+    UncheckedWithoutCheck,
+    /// A mutable account (that isn't a signer, being created or a `Program`) with no `owner`,
+    /// `has_one`, `seeds`, `address` or `constraint` constraint tying it to the program or the
+    /// other accounts
+    UnconstrainedMut,
+    /// An `init` (or `init_if_needed`) account whose payer is missing or isn't a signer
+    InitWithoutSignerPayer,
+    /// A `close` constraint whose target, which receives the lamports, isn't mutable
+    CloseTargetNotMut,
+    /// An instruction without any signer, which anyone can call
+    NoSigner,
+}
+
+impl Rule {
+    /// Name of the rule, as printed with its findings, e.g. `unconstrained-mut`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UncheckedWithoutCheck => "unchecked-without-check",
+            Rule::UnconstrainedMut => "unconstrained-mut",
+            Rule::InitWithoutSignerPayer => "init-without-signer-payer",
+            Rule::CloseTargetNotMut => "close-target-not-mut",
+            Rule::NoSigner => "no-signer",
+        }
+    }
+}
+
+/// Constraints that make a mutable account safe enough for `Rule::UnconstrainedMut`: those that
+/// check who owns it or which account it is, custom ones, and those that create it.
+const MUT_CONSTRAINTS: &[&str] = &[
+    "owner",
+    "has_one",
+    "seeds",
+    "address",
+    "constraint",
+    "init",
+    "init_if_needed",
+    "zero",
+];
+
+/// Account types that anchor checks to be one exact account, e.g. a `Program<System>` is the
+/// system program, which is enough for `Rule::UnconstrainedMut`. An `Account<Vault>` is only
+/// checked to be owned by the program, so another user's vault would pass, and isn't in here.
+const CHECKED_TYPES: &[&str] = &["Program"];
+
+/// A finding of a lint rule on an instruction or one of its accounts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub instruction: String,
+    /// Group path of the account in the instruction, e.g. `transfer.authority` (see
+    /// `diff::account_paths`), if the finding is about one
+    pub account: Option<String>,
+    pub message: String,
+    /// Where the account (or instruction) is declared, if the program was read from source
+    pub location: Option<Location>,
+}
+
+/// A diagnostic in the manner of rustc: `warning[rule]: message` and, if known, where.
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.rule.name(), self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, "\n  --> {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+/// Findings of all rules on the instructions of `program`, in instruction order. The rules on
+/// constraints and `/// CHECK:` comments need the program source, so a program loaded from an
+/// idl json only gets `Rule::NoSigner` findings.
+pub fn lint(program: &Program) -> Vec<Finding> {
+    let mut findings = vec![];
+    for ix in &program.idl.instructions {
+        let info = program.source.accounts(&ix.name);
+        let mut linter = Linter {
+            instruction: &ix.name,
+            findings: &mut findings,
+        };
+        linter.accounts(&ix.accounts, info, None);
+        if !any_signer(&ix.accounts) {
+            findings.push(Finding {
+                rule: Rule::NoSigner,
+                instruction: ix.name.clone(),
+                account: None,
+                message: format!("`{}` has no signer, so anyone can call it", ix.name),
+                location: program.source.locations.get(&ix.name).cloned(),
+            });
+        }
+    }
+    findings
+}
+
+struct Linter<'a> {
+    instruction: &'a str,
+    findings: &'a mut Vec<Finding>,
+}
+
+impl Linter<'_> {
+    /// Lints the accounts `items` of an instruction or of the account group at path `group` in
+    /// it, as the source `info` has them.
+    fn accounts(&mut self, items: &[IdlAccountItem], info: &AccountsInfo, group: Option<&str>) {
+        let path = |name: &str| match group {
+            Some(group) => format!("{}.{}", group, name),
+            None => name.to_string(),
+        };
+        let siblings: Vec<&IdlAccount> = items
+            .iter()
+            .filter_map(|item| match item {
+                IdlAccountItem::IdlAccount(account) => Some(account),
+                IdlAccountItem::IdlAccounts(_) => None,
+            })
+            .collect();
+        for item in items {
+            match item {
+                IdlAccountItem::IdlAccount(account) => {
+                    if let Some(field) = info.field(&account.name) {
+                        self.account(account, field, &siblings, &path(&account.name));
+                    }
+                }
+                IdlAccountItem::IdlAccounts(group) => {
+                    let group_path = path(&group.name);
+                    self.accounts(&group.accounts, info.group(&group.name), Some(&group_path));
+                }
+            }
+        }
+    }
+
+    /// Lints the account at group `path`, whose constraints refer to the other accounts of its
+    /// struct by their source (snake_case) name.
+    fn account(
+        &mut self,
+        account: &IdlAccount,
+        field: &FieldInfo,
+        siblings: &[&IdlAccount],
+        path: &str,
+    ) {
+        let constraint = |name: &str| field.constraints.iter().find(|c| c.name == name);
+        let sibling = |name: &str| {
+            let name = name.to_mixed_case();
+            siblings
+                .iter()
+                .find(|sibling| sibling.name == name)
+                .copied()
+        };
+
+        // findings name the account by its path in the program, e.g. `deposit.transfer.from`
+        let qualified = format!("{}.{}", self.instruction, path);
+        let checked = field
+            .doc_lines
+            .iter()
+            .any(|line| line.trim_start().starts_with("CHECK"));
+        if field.is_unchecked() && !checked {
+            let ty = field.ty.as_deref().unwrap_or_default();
+            let message = format!(
+                "`{}` is an {} without a `/// CHECK:` comment saying why it's safe",
+                qualified, ty
+            );
+            self.push(Rule::UncheckedWithoutCheck, path, field, message);
+        }
+
+        let constrained = MUT_CONSTRAINTS
+            .iter()
+            .any(|name| constraint(name).is_some());
+        if account.is_mut && !account.is_signer && !constrained && !checked_type(field) {
+            let message = format!(
+                "`{}` is mutable but has no owner, has_one or seeds constraint",
+                qualified
+            );
+            self.push(Rule::UnconstrainedMut, path, field, message);
+        }
+
+        if constraint("init")
+            .or_else(|| constraint("init_if_needed"))
+            .is_some()
+        {
+            let payer = constraint("payer").and_then(|payer| payer.value.as_deref());
+            let message = match payer {
+                None => Some(format!("`{}` is initialized without a payer", qualified)),
+                Some(payer) if !matches!(sibling(payer), Some(payer) if payer.is_signer) => {
+                    Some(format!(
+                        "`{}` is initialized with payer `{}`, which isn't a signer",
+                        qualified, payer
+                    ))
+                }
+                Some(_) => None,
+            };
+            if let Some(message) = message {
+                self.push(Rule::InitWithoutSignerPayer, path, field, message);
+            }
+        }
+
+        if let Some(target) = constraint("close").and_then(|close| close.value.as_deref()) {
+            if !matches!(sibling(target), Some(target) if target.is_mut) {
+                let message = format!(
+                    "`{}` is closed into `{}`, which isn't mutable",
+                    qualified, target
+                );
+                self.push(Rule::CloseTargetNotMut, path, field, message);
+            }
+        }
+    }
+
+    fn push(&mut self, rule: Rule, path: &str, field: &FieldInfo, message: String) {
+        self.findings.push(Finding {
+            rule,
+            instruction: self.instruction.to_string(),
+            account: Some(path.to_string()),
+            message,
+            location: field.location.clone(),
+        });
+    }
+}
+
+fn any_signer(items: &[IdlAccountItem]) -> bool {
+    items.iter().any(|item| match item {
+        IdlAccountItem::IdlAccount(account) => account.is_signer,
+        IdlAccountItem::IdlAccounts(group) => any_signer(&group.accounts),
+    })
+}
+
+/// Whether `field`'s account is of a type that anchor checks to be one account (see
+/// `CHECKED_TYPES`), boxed or not.
+fn checked_type(field: &FieldInfo) -> bool {
+    let ty = field.ty.as_deref().unwrap_or_default();
+    let ty = ty.strip_prefix("Box<").unwrap_or(ty);
+    CHECKED_TYPES
+        .iter()
+        .any(|checked| ty.starts_with(&format!("{}<", checked)))
+}
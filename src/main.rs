@@ -1,17 +1,90 @@
-use anyhow::Result;
-
-pub mod viz;
+use anchor_viz::{error, theme, viz};
 
 /// This function parses command line arguments and passes them
 /// into the visualization workflow
 ///
 /// Arguments:
 /// --program-name (-p) program_name
-fn main() -> Result<()> {
+/// --idl (-i) path/to/idl.json (or - for stdin)
+/// --width (-w) width
+/// --format (-f) png|svg|html|mermaid|dot|layout-json|csv
+/// --seeds (-s)
+/// --all (-a)
+/// --rev (-r) git-ref
+/// --out (-o) path/with/{name}-{version}.{format}
+/// --out-dir (-d) dir
+/// --fit shrink|widen
+/// --footnotes
+/// --theme light|dark|colorblind-safe|path/to/theme.toml
+///
+/// Subcommands:
+/// diff old new (each a program source, IDL json or git revision)
+/// check-compat old new
+/// matrix [program] (a program source, IDL json or git revision)
+/// lint [program]
+///
+/// On failure the error is printed and the process exits with the code of its kind
+/// (see `error::VizError::exit_code`).
+fn main() {
     // Parse args
     let args = Args::parse();
+    // subcommands read git revisions as their programs, so --rev is only for rendering
+    if args.rev.is_some() && args.command.is_some() {
+        let message = "--rev can't be used with a subcommand, pass the revision as its program\n";
+        clap::Error::raw(clap::ErrorKind::ArgumentConflict, message).exit();
+    }
 
-    viz::visual(args.program_name, args.width)
+    let result = std::env::current_dir()
+        .map_err(Into::into)
+        .and_then(|dir| {
+            // --theme, or else the [viz] section of the workspace's Anchor.toml
+            let template = match args.command {
+                Some(Command::Diff { .. }) => viz::DEFAULT_DIFF_OUT_TEMPLATE,
+                Some(Command::Matrix { .. }) => viz::DEFAULT_MATRIX_OUT_TEMPLATE,
+                Some(Command::Lint { .. }) => viz::DEFAULT_LINT_OUT_TEMPLATE,
+                _ => viz::DEFAULT_OUT_TEMPLATE,
+            };
+            let options = viz::RenderOptions {
+                seeds: args.seeds,
+                footnotes: args.footnotes,
+                fit: args.fit,
+                theme: theme::Theme::resolve(args.theme.as_deref(), &dir)?,
+                ..viz::RenderOptions::new(args.format)
+            };
+            let output = viz::Output {
+                template: args.out.unwrap_or_else(|| template.to_string()),
+                dir: args.out_dir.unwrap_or_default(),
+                options: options.with_width(args.width)?,
+            };
+            let program_name = args.program_name.as_deref();
+            if let Some(Command::Diff { old, new }) = &args.command {
+                viz::visual_diff(old, new, program_name, &output)
+            } else if let Some(Command::CheckCompat { old, new }) = &args.command {
+                viz::check_compat(old, new, program_name, args.seeds)
+            } else if let Some(Command::Matrix { program }) = &args.command {
+                viz::visual_matrix(program, program_name, &output)
+            } else if let Some(Command::Lint { program }) = &args.command {
+                // the findings as diagnostics, failing if there are any
+                let findings = viz::visual_lint(program, program_name, &output)?;
+                for finding in &findings {
+                    println!("{}\n", finding);
+                }
+                match findings.len() {
+                    0 => Ok(()),
+                    count => Err(error::VizError::LintFindings { count }.into()),
+                }
+            } else if let Some(rev) = &args.rev {
+                viz::visual_rev(rev, program_name, &output)
+            } else if args.all {
+                viz::visual_all(&dir, &output)
+            } else {
+                viz::visual(args.program_name, args.idl, &output)
+            }
+        });
+    if let Err(err) = result {
+        eprintln!("error: {:#}", err);
+        std::process::exit(error::exit_code(&err));
+    }
 }
 
 use clap::Parser;
@@ -19,22 +92,113 @@ use clap::Parser;
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Name of programto visualize
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     program_name: Option<String>,
 
+    /// Path to an IDL json file (e.g. target/idl/my_program.json) to render
+    /// instead of parsing the program source. Use - to read from stdin
+    #[clap(short, long)]
+    idl: Option<String>,
+
     /// Number of accounts, arguments per instruction column
-    #[clap(short, long, default_value_t = 2)]
+    #[clap(short, long, default_value_t = 2, global = true)]
     width: usize,
-}
 
-#[test]
-fn test_0() {
-    viz::visual(Some("test_0/programs/test_0".to_string()), 2).unwrap();
-}
+    /// Output format of the visualization
+    #[clap(short, long, arg_enum, default_value = "png", global = true)]
+    format: viz::Format,
+
+    /// Parse the program with the seeds feature first and list
+    /// the seeds of each PDA account
+    #[clap(short, long, global = true)]
+    seeds: bool,
+
+    /// Visualize every program of the anchor workspace in the current dir
+    /// (and write an index, anchor-viz.md)
+    #[clap(short, long, conflicts_with_all = &["program-name", "idl"])]
+    all: bool,
 
-#[test]
-fn test_1() {
-    viz::visual(Some("test_1/programs/test_1".to_string()), 2).unwrap();
+    /// Render the program as of a git revision (e.g. a tag like v1.0.0) of
+    /// the repository in the current dir, read from the local repository.
+    /// Subcommands take a revision as their program instead
+    #[clap(short, long, conflicts_with_all = &["idl", "all"])]
+    rev: Option<String>,
+
+    /// Output path. {name}, {version} and {format} are replaced by the
+    /// program name, idl version and file extension.
+    /// [default: {name}.{format}, or {name}-diff.{format} for a diff and
+    /// {name}-lint.{format} for a lint]
+    #[clap(short, long, global = true)]
+    out: Option<String>,
+
+    /// Directory to write output to (created if needed)
+    #[clap(short = 'd', long, global = true)]
+    out_dir: Option<std::path::PathBuf>,
+
+    /// How names too long for their box are fitted: shrink (then wrap, then
+    /// cut short) the text, or widen all boxes to the widest name
+    #[clap(long, arg_enum, default_value = "shrink", global = true)]
+    fit: viz::Fit,
+
+    /// List the doc comments of instructions, accounts and args under each
+    /// column as numbered footnotes (png, svg)
+    #[clap(long, global = true)]
+    footnotes: bool,
+
+    /// Colors, font and sizes: a built-in theme (light, dark, colorblind-safe)
+    /// or a theme file. Defaults to the [viz] section of Anchor.toml
+    #[clap(short, long, global = true)]
+    theme: Option<String>,
 }
 
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Visualize what changed between two versions of a program: its
+    /// instructions, accounts and args colored as added, removed or changed
+    /// (png, svg, layout-json)
+    Diff {
+        /// Old version: a program source, an IDL json or a git revision
+        /// (e.g. main, v1.0.0) of the program in the current dir
+        old: String,
+
+        /// New version, like the old one (e.g. . for the working tree)
+        new: String,
+    },
+
+    /// Report which changes between two versions of a program break its
+    /// clients (e.g. reordered accounts or args, changed type layouts,
+    /// renamed instructions), exiting with code 9 if any do
+    CheckCompat {
+        /// Old version: a program source, an IDL json or a git revision
+        old: String,
+
+        /// New version, like the old one
+        new: String,
+    },
+
+    /// Draw which instructions use which accounts as a matrix, with the
+    /// access (S signer, W writable, R read-only) in each cell (png, svg,
+    /// csv, layout-json)
+    Matrix {
+        /// A program source, an IDL json or a git revision of the program in
+        /// the current dir
+        #[clap(default_value = ".")]
+        program: String,
+    },
+
+    /// Look for common Anchor pitfalls (unchecked accounts without a CHECK
+    /// comment, unconstrained mutable accounts, init without a signer payer,
+    /// close into an immutable account, instructions without a signer),
+    /// print them and mark them on the visualization (png, svg,
+    /// layout-json), exiting with code 10 if there are any
+    Lint {
+        /// A program source, an IDL json or a git revision of the program in
+        /// the current dir
+        #[clap(default_value = ".")]
+        program: String,
+    },
+}
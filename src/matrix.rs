@@ -0,0 +1,282 @@
+use crate::diff::flatten;
+use crate::error::VizError;
+use crate::layout::{text_px_width, Align, Line, Rect};
+use crate::theme::Theme;
+use crate::viz::Role;
+use anchor_syn::idl::Idl;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+/// Width of the grid lines between cells.
+const GRID_PX_WIDTH: usize = 1;
+
+/// How an instruction accesses an account, from the flags of its accounts of that name (those of
+/// account groups included).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Access {
+    pub signer: bool,
+    pub writable: bool,
+}
+
+impl Access {
+    /// `S` (signer), `W` (writable), `SW` (both) or `R` (read-only).
+    pub fn code(&self) -> &'static str {
+        match (self.signer, self.writable) {
+            (true, true) => "SW",
+            (true, false) => "S",
+            (false, true) => "W",
+            (false, false) => "R",
+        }
+    }
+
+    /// Role whose color the access is drawn in; signing wins over mutability.
+    pub fn role(&self) -> Role {
+        if self.signer {
+            Role::Signer
+        } else if self.writable {
+            Role::Mut
+        } else {
+            Role::Immut
+        }
+    }
+}
+
+/// Which instructions use which accounts, and how: a row per instruction (and state method, as
+/// `State.method`) and a column per distinct account name, in order of first use.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AccountMatrix {
+    pub name: String,
+    pub version: String,
+    pub instructions: Vec<String>,
+    pub accounts: Vec<String>,
+    /// Access of each instruction (row) to each account (column), if it uses it
+    pub cells: Vec<Vec<Option<Access>>>,
+}
+
+impl AccountMatrix {
+    /// The matrix as csv: a header row of `instruction` and the account names, then a row per
+    /// instruction with the access codes (see `Access::code`), empty where unused.
+    pub fn csv(&self) -> String {
+        let mut csv = String::new();
+        let header = std::iter::once("instruction").chain(self.accounts.iter().map(String::as_str));
+        csv_row(&mut csv, header);
+        for (instruction, row) in self.instructions.iter().zip(&self.cells) {
+            let codes = row
+                .iter()
+                .map(|cell| cell.as_ref().map_or("", Access::code));
+            csv_row(&mut csv, std::iter::once(instruction.as_str()).chain(codes));
+        }
+        csv
+    }
+}
+
+/// The account usage matrix of `idl`.
+pub fn account_matrix(idl: &Idl) -> AccountMatrix {
+    let state_name = idl
+        .state
+        .as_ref()
+        .map(|state| state.strct.name.clone())
+        .unwrap_or_default();
+    let instructions = idl.instructions.iter().map(|ix| (ix.name.clone(), ix));
+    let state_methods = idl.state.iter().flat_map(|state| {
+        state
+            .methods
+            .iter()
+            .map(|m| (format!("{}.{}", state_name, m.name), m))
+    });
+
+    let mut matrix = AccountMatrix {
+        name: idl.name.clone(),
+        version: idl.version.clone(),
+        instructions: vec![],
+        accounts: vec![],
+        cells: vec![],
+    };
+    for (name, instruction) in instructions.chain(state_methods) {
+        let mut row = vec![None; matrix.accounts.len()];
+        for account in flatten(&instruction.accounts) {
+            let column = match matrix.accounts.iter().position(|a| *a == account.name) {
+                Some(column) => column,
+                None => {
+                    matrix.accounts.push(account.name.clone());
+                    row.push(None);
+                    row.len() - 1
+                }
+            };
+            let access = row[column].get_or_insert(Access {
+                signer: false,
+                writable: false,
+            });
+            access.signer |= account.is_signer;
+            access.writable |= account.is_mut;
+        }
+        matrix.instructions.push(name);
+        matrix.cells.push(row);
+    }
+    // rows before an account's first use are shorter
+    for row in &mut matrix.cells {
+        row.resize(matrix.accounts.len(), None);
+    }
+    matrix
+}
+
+/// Writes the csv of the account matrix of `idl` (see `AccountMatrix::csv`) to `out`.
+pub fn write_csv(idl: &Idl, out: &Path) -> Result<()> {
+    std::fs::write(out, account_matrix(idl).csv())
+        .map_err(|err| VizError::backend_write("couldn't write account matrix", err).into())
+}
+
+/// A cell of the matrix drawn for an instruction's use of an account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MatrixCell {
+    pub rect: Rect,
+    pub role: Role,
+    pub code: Line,
+}
+
+/// Pixel layout of an account matrix, in the manner of `layout::Layout`: the title and a legend
+/// of the access codes above a grid with the account names as header row and the instruction
+/// names as first column.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MatrixLayout {
+    pub width: usize,
+    pub height: usize,
+    pub title: Line,
+    pub legend: Line,
+    /// Account names, centered over their column
+    pub accounts: Vec<Line>,
+    /// Instruction names, left of their row
+    pub instructions: Vec<Line>,
+    pub cells: Vec<MatrixCell>,
+    /// Lines between the rows and columns
+    pub grid: Vec<Rect>,
+    pub theme: Theme,
+}
+
+/// Lays out `matrix` in `theme`: columns are as wide as their account name, rows as high as
+/// half a box.
+pub fn layout(matrix: &AccountMatrix, theme: &Theme) -> MatrixLayout {
+    let (family, size) = (theme.font.family.as_str(), theme.font.small);
+    let spacing = theme.sizes.spacing;
+    let row_height = theme.sizes.box_height / 2;
+    let text_width = |text: &str| text_px_width(text, family, size) + 2 * spacing;
+    let line = |text: &str, x: usize, y: usize, align: Align| Line {
+        text: text.to_string(),
+        x,
+        y,
+        align,
+        size,
+    };
+
+    let names_width = matrix
+        .instructions
+        .iter()
+        .map(|name| text_width(name))
+        .max()
+        .unwrap_or_default();
+    // left edges of the account columns, then the right edge of the last
+    let mut edges = vec![spacing + names_width];
+    for account in &matrix.accounts {
+        let width = text_width(account).max(text_width("SW"));
+        edges.push(edges[edges.len() - 1] + width);
+    }
+    let table_right = edges[edges.len() - 1];
+    let top = theme.sizes.header;
+    let table_bottom = top + row_height * (matrix.instructions.len() + 1);
+    let title = format!("Account usage: {} {}", matrix.name, matrix.version);
+    let title_width = text_px_width(&title, family, theme.font.title) + 2 * spacing;
+    let width = (table_right + spacing).max(title_width);
+
+    let accounts = matrix
+        .accounts
+        .iter()
+        .zip(edges.windows(2))
+        .map(|(account, edge)| {
+            line(
+                account,
+                (edge[0] + edge[1]) / 2,
+                top + row_height / 2,
+                Align::Center,
+            )
+        })
+        .collect();
+    let mut instructions = vec![];
+    let mut cells = vec![];
+    for (i, (instruction, row)) in matrix.instructions.iter().zip(&matrix.cells).enumerate() {
+        let row_top = top + row_height * (i + 1);
+        let y = row_top + row_height / 2;
+        instructions.push(line(instruction, 2 * spacing, y, Align::Left));
+        for (access, edge) in row.iter().zip(edges.windows(2)) {
+            if let Some(access) = access {
+                let rect = Rect {
+                    left: edge[0] + GRID_PX_WIDTH,
+                    top: row_top + GRID_PX_WIDTH,
+                    width: edge[1] - edge[0] - GRID_PX_WIDTH,
+                    height: row_height - GRID_PX_WIDTH,
+                };
+                cells.push(MatrixCell {
+                    rect,
+                    role: access.role(),
+                    code: line(access.code(), rect.center_x(), y, Align::Center),
+                });
+            }
+        }
+    }
+
+    let mut grid = vec![];
+    for row in 0..=matrix.instructions.len() + 1 {
+        grid.push(Rect {
+            left: spacing,
+            top: top + row_height * row,
+            width: table_right - spacing,
+            height: GRID_PX_WIDTH,
+        });
+    }
+    for x in std::iter::once(spacing).chain(edges.iter().copied()) {
+        grid.push(Rect {
+            left: x,
+            top,
+            width: GRID_PX_WIDTH,
+            height: table_bottom - top + GRID_PX_WIDTH,
+        });
+    }
+
+    MatrixLayout {
+        width,
+        height: table_bottom + spacing,
+        title: Line {
+            text: title,
+            x: width / 2,
+            y: top / 3,
+            align: Align::Center,
+            size: theme.font.title,
+        },
+        legend: line(
+            "S signer, W writable, SW both, R read-only",
+            width / 2,
+            top * 2 / 3,
+            Align::Center,
+        ),
+        accounts,
+        instructions,
+        cells,
+        grid,
+        theme: theme.clone(),
+    }
+}
+
+/// Appends a csv row of `fields`, quoting those that need it.
+fn csv_row<'a>(csv: &mut String, fields: impl Iterator<Item = &'a str>) {
+    let fields: Vec<String> = fields
+        .map(|field| {
+            if field.contains(&[',', '"', '\n'][..]) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+}
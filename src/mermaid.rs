@@ -0,0 +1,141 @@
+use crate::error::VizError;
+use crate::theme::Theme;
+use crate::types;
+use crate::viz::Role;
+use anchor_syn::idl::{Idl, IdlAccountItem, IdlInstruction};
+use anyhow::Result;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Writes the mermaid flowchart of `idl` (see `flowchart`) to `out`.
+pub fn write(idl: &Idl, out: &Path, theme: &Theme) -> Result<()> {
+    std::fs::write(out, flowchart(idl, theme))
+        .map_err(|err| VizError::backend_write("couldn't write mermaid diagram", err).into())
+}
+
+/// Mermaid flowchart of the instructions (and state methods) of `idl`.
+///
+/// Each instruction is a subgraph holding the instruction node, linked to its account nodes
+/// (account groups become nested subgraphs) and, dotted, to its args. Nodes are colored through
+/// one classDef per role, with the colors of `theme` as in the png.
+pub fn flowchart(idl: &Idl, theme: &Theme) -> String {
+    let mut chart = String::from("flowchart TB\n");
+    for role in Role::ALL {
+        writeln!(
+            chart,
+            "    classDef {} fill:{},stroke:{},color:{}",
+            role.name(),
+            theme.color(role).hex(),
+            theme.colors.foreground.hex(),
+            theme.colors.text.hex()
+        )
+        .unwrap();
+    }
+
+    let state_name = idl
+        .state
+        .as_ref()
+        .map(|state| state.strct.name.clone())
+        .unwrap_or_default();
+    let instructions = idl
+        .instructions
+        .iter()
+        .map(|ix| ("Instruction:", ix.name.clone(), ix));
+    let state_methods = idl.state.iter().flat_map(|state| {
+        state
+            .methods
+            .iter()
+            .map(|m| ("State Method:", format!("{}.{}", state_name, m.name), m))
+    });
+
+    for (i, (label, name, instruction)) in instructions.chain(state_methods).enumerate() {
+        write_instruction(&mut chart, &format!("ix{}", i), label, &name, instruction);
+    }
+    chart
+}
+
+fn write_instruction(
+    chart: &mut String,
+    id: &str,
+    label: &str,
+    name: &str,
+    instruction: &IdlInstruction,
+) {
+    writeln!(chart, "    subgraph {}_column [\"{}\"]", id, escape(name)).unwrap();
+    writeln!(
+        chart,
+        "        {}[\"{}<br/>{}\"]:::{}",
+        id,
+        label,
+        escape(name),
+        Role::Instruction.name()
+    )
+    .unwrap();
+    let mut counter = 0;
+    write_accounts(chart, id, &instruction.accounts, &mut counter, 2);
+    for (a, arg) in instruction.args.iter().enumerate() {
+        writeln!(
+            chart,
+            "        {}_arg{}[\"{}<br/>{}\"]:::{}",
+            id,
+            a,
+            escape(&format!("{}:", types::type_label(&arg.ty))),
+            escape(&arg.name),
+            Role::Arg.name()
+        )
+        .unwrap();
+        writeln!(chart, "        {} -.- {}_arg{}", id, id, a).unwrap();
+    }
+    writeln!(chart, "    end").unwrap();
+}
+
+/// Writes account nodes (and account groups as nested subgraphs) linked to instruction node `id`.
+/// `counter` keeps node ids unique across groups.
+fn write_accounts(
+    chart: &mut String,
+    id: &str,
+    items: &[IdlAccountItem],
+    counter: &mut usize,
+    depth: usize,
+) {
+    let indent = "    ".repeat(depth);
+    for item in items {
+        let node = format!("{}_acc{}", id, counter);
+        *counter += 1;
+        match item {
+            IdlAccountItem::IdlAccount(account) => {
+                let (label, role) = match (account.is_signer, account.is_mut) {
+                    (true, true) => ("Mutable Signer:", Role::Signer),
+                    (true, false) => ("Signer:", Role::Signer),
+                    (false, true) => ("Mutable Account:", Role::Mut),
+                    (false, false) => ("Immutable Account:", Role::Immut),
+                };
+                writeln!(
+                    chart,
+                    "{}{}[\"{}<br/>{}\"]:::{}",
+                    indent,
+                    node,
+                    label,
+                    escape(&account.name),
+                    role.name()
+                )
+                .unwrap();
+                writeln!(chart, "{}{} --- {}", indent, id, node).unwrap();
+            }
+            IdlAccountItem::IdlAccounts(group) => {
+                writeln!(chart, "{}subgraph {} [\"{}\"]", indent, node, escape(&group.name))
+                    .unwrap();
+                write_accounts(chart, id, &group.accounts, counter, depth + 1);
+                writeln!(chart, "{}end", indent).unwrap();
+            }
+        }
+    }
+}
+
+/// Escapes characters that would end or garble a quoted mermaid label.
+fn escape(label: &str) -> String {
+    label
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
@@ -0,0 +1,813 @@
+use crate::error::VizError;
+use anchor_syn::parser::accounts as accounts_parser;
+use anchor_syn::{AccountField, AccountsStruct, ConstraintToken};
+use anyhow::Result;
+use heck::MixedCase;
+use quote::ToTokens;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+
+/// Badge code, constraint name (as written in `#[account(...)]`) and legend text of every
+/// constraint that gets a badge. `mut` and `signer` have none as they already set the box color.
+/// Namespaced constraints (`token::mint`, `realloc::payer`, ..) share the badge of their namespace.
+pub const BADGES: &[(&str, &str, &str)] = &[
+    ("I", "init", "init"),
+    ("I?", "init_if_needed", "init_if_needed"),
+    ("Z", "zero", "zero (uninitialized)"),
+    ("P", "payer", "payer = account"),
+    ("SP", "space", "space"),
+    ("S", "seeds", "seeds + bump"),
+    ("H", "has_one", "has_one = account"),
+    ("C", "constraint", "constraint = expression"),
+    ("A", "address", "address = key"),
+    ("O", "owner", "owner = program"),
+    ("X", "close", "close = account"),
+    ("R", "realloc", "realloc"),
+    ("T", "token", "token account"),
+    ("M", "mint", "mint account"),
+    ("AT", "associated_token", "associated token account"),
+    ("E", "executable", "executable"),
+];
+
+/// Constraints whose value names another account, shown on the badge as `CODE:account`.
+const ACCOUNT_VALUED: &[&str] = &["payer", "has_one", "close"];
+
+/// One constraint of an account field, e.g. `payer = user` or `init`, as written in the source.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Constraint {
+    /// Constraint name, e.g. `has_one` or `token::mint`
+    pub name: String,
+    /// Right-hand side, if any, e.g. `user`
+    pub value: Option<String>,
+}
+
+impl Constraint {
+    /// Compact badge for this constraint (see `BADGES`), if it gets one.
+    pub fn badge(&self) -> Option<String> {
+        let namespace = self.name.split("::").next().unwrap_or_default();
+        let (code, _, _) = BADGES.iter().find(|(_, name, _)| *name == namespace)?;
+        match &self.value {
+            Some(value) if ACCOUNT_VALUED.contains(&self.name.as_str()) => {
+                Some(format!("{}:{}", code, value))
+            }
+            _ => Some(code.to_string()),
+        }
+    }
+}
+
+/// Where something is declared in the program source, e.g. for diagnostics.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub file: PathBuf,
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+}
+
+impl Location {
+    /// Location of `ident`, as parsed from `file`.
+    fn of(file: &Path, ident: &syn::Ident) -> Self {
+        let start = ident.span().start();
+        Location {
+            file: file.to_path_buf(),
+            line: start.line,
+            column: start.column + 1,
+        }
+    }
+}
+
+/// `file:line:column`, as compilers write it
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+/// Account types that anchor doesn't check at all, which is why they need a `/// CHECK:` comment.
+const UNCHECKED_TYPES: &[&str] = &["UncheckedAccount", "AccountInfo"];
+
+/// What the source says about one account field of an instruction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FieldInfo {
+    pub constraints: Vec<Constraint>,
+    /// Declared anchor type without lifetimes, e.g. `Account<Vault>`, `Signer` or `Box<Account<Vault>>`
+    pub ty: Option<String>,
+    /// Doc comment of the field (see `doc_comment`), e.g. its `/// CHECK:` comment
+    pub doc: Option<String>,
+    /// Lines of the doc comment as written (see `doc_lines`), e.g. to find a `CHECK:` line that
+    /// isn't the first
+    pub doc_lines: Vec<String>,
+    /// Where the field is declared, if its file was found
+    pub location: Option<Location>,
+}
+
+impl FieldInfo {
+    /// Whether the account is an `UncheckedAccount` or `AccountInfo`, i.e. unchecked by anchor.
+    pub fn is_unchecked(&self) -> bool {
+        self.ty
+            .as_deref()
+            .map(|ty| {
+                UNCHECKED_TYPES
+                    .iter()
+                    .any(|unchecked| ty == *unchecked || ty.starts_with(&format!("{}<", unchecked)))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Badges of this account's constraints, one per badge code.
+    pub fn badges(&self) -> Vec<String> {
+        let mut badges: Vec<String> = vec![];
+        for badge in self.constraints.iter().filter_map(Constraint::badge) {
+            let code = badge.split(':').next().unwrap_or_default();
+            let account_valued = badge.contains(':');
+            // a namespace (or seeds + bump) is one badge, but every has_one gets its own
+            if account_valued || !badges.iter().any(|b| b == code) {
+                badges.push(badge);
+            }
+        }
+        badges
+    }
+}
+
+/// Account fields of an instruction (or account group), keyed by their idl (mixedCase) name,
+/// with composite account groups nested.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct AccountsInfo {
+    pub fields: BTreeMap<String, FieldInfo>,
+    pub groups: BTreeMap<String, AccountsInfo>,
+}
+
+impl AccountsInfo {
+    /// Field info of account `name`, if the source had it.
+    pub fn field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields.get(name)
+    }
+
+    /// Account group `name`, or an empty one if the source didn't have it.
+    pub fn group(&self, name: &str) -> &AccountsInfo {
+        self.groups.get(name).unwrap_or(&NO_ACCOUNTS)
+    }
+
+    /// Field info of account `name` here or in any group, e.g. for signers, which are drawn
+    /// outside of their groups.
+    pub fn find_field(&self, name: &str) -> Option<&FieldInfo> {
+        self.field(name)
+            .or_else(|| self.groups.values().find_map(|group| group.find_field(name)))
+    }
+
+    fn rebase_locations(&mut self, rebase: &dyn Fn(&Path) -> PathBuf) {
+        let fields = self.fields.values_mut();
+        for location in fields.filter_map(|field| field.location.as_mut()) {
+            location.file = rebase(&location.file);
+        }
+        for group in self.groups.values_mut() {
+            group.rebase_locations(rebase);
+        }
+    }
+}
+
+/// Doc comments of an instruction: its own, without the `# Arguments` section, and those of its
+/// args from that section (keyed by their idl name), as rustdoc has them:
+///
+/// ```text
+/// /// Moves `amount` out of the vault.
+/// ///
+/// /// # Arguments
+/// ///
+/// /// * `amount` - lamports to withdraw
+/// ```
+///
+/// Doc comments can't go on function parameters, so that section is where args are documented.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct InstructionDocs {
+    pub doc: Option<String>,
+    pub args: BTreeMap<String, String>,
+}
+
+static NO_DOCS: InstructionDocs = InstructionDocs {
+    doc: None,
+    args: BTreeMap::new(),
+};
+
+static NO_ACCOUNTS: AccountsInfo = AccountsInfo {
+    fields: BTreeMap::new(),
+    groups: BTreeMap::new(),
+};
+
+/// What the program source says beyond its idl, per instruction (keyed by the idl name).
+/// Empty when rendering from an idl json, as there is no source to read.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SourceInfo {
+    pub instructions: BTreeMap<String, AccountsInfo>,
+    /// Doc comments of the instructions
+    pub docs: BTreeMap<String, InstructionDocs>,
+    /// Where the instructions are declared
+    pub locations: BTreeMap<String, Location>,
+}
+
+impl SourceInfo {
+    /// Reads the `#[derive(Accounts)]` structs used by the `#[program]` instructions of the
+    /// program at `lib` (its `src/lib.rs`), the doc comments of the instructions and their
+    /// accounts and args, and where the instructions and accounts are declared.
+    ///
+    /// Accounts structs are parsed by anchor (see `anchor_syn::parser::accounts::parse`). This
+    /// only adds to the idl, so it's best effort: modules that can't be read and structs that
+    /// anchor rejects or that can't be told apart from others of the same name are skipped, and
+    /// their instructions get no source info. Only reading `lib` itself can fail.
+    pub fn parse(lib: &Path) -> Result<Self> {
+        let modules = Module::parse_crate(lib).map_err(|source| VizError::IdlParse {
+            path: lib.display().to_string(),
+            source,
+        })?;
+        let structs = AccountsStructs::new(&modules);
+
+        let mut info = SourceInfo::default();
+        let program_mod = modules.iter().find_map(|module| {
+            module.items.iter().find_map(|item| match item {
+                syn::Item::Mod(item_mod) if has_attr(&item_mod.attrs, "program") => {
+                    Some((module, item_mod))
+                }
+                _ => None,
+            })
+        });
+        let (module, program_mod) = match program_mod {
+            Some(program_mod) => program_mod,
+            None => return Ok(info),
+        };
+        let path = format!("{}::{}", module.path, program_mod.ident);
+        let items = program_mod
+            .content
+            .as_ref()
+            .map(|(_, items)| items.as_slice())
+            .unwrap_or_default();
+        for item in items {
+            if let syn::Item::Fn(method) = item {
+                let accounts = match context_accounts(method) {
+                    Some(accounts) => accounts,
+                    None => continue,
+                };
+                let name = method.sig.ident.to_string().to_mixed_case();
+                let uses = use_paths(&path, items);
+                if let Some(key) = structs.resolve(&accounts, &path, &uses) {
+                    let accounts = structs.accounts_info(key, 0);
+                    info.instructions.insert(name.clone(), accounts);
+                }
+                info.docs
+                    .insert(name.clone(), instruction_docs(&method.attrs));
+                let location = Location::of(&module.file, &method.sig.ident);
+                info.locations.insert(name, location);
+            }
+        }
+        Ok(info)
+    }
+
+    /// Accounts of `instruction`, or none if the source didn't have it.
+    pub fn accounts(&self, instruction: &str) -> &AccountsInfo {
+        self.instructions.get(instruction).unwrap_or(&NO_ACCOUNTS)
+    }
+
+    /// Replaces the file of every location with `rebase(file)`, e.g. to point out of a
+    /// temporary checkout the source was read from.
+    pub fn rebase_locations(&mut self, rebase: &dyn Fn(&Path) -> PathBuf) {
+        for location in self.locations.values_mut() {
+            location.file = rebase(&location.file);
+        }
+        for accounts in self.instructions.values_mut() {
+            accounts.rebase_locations(rebase);
+        }
+    }
+
+    /// Doc comments of `instruction`, or none if the source didn't have it.
+    pub fn docs(&self, instruction: &str) -> &InstructionDocs {
+        self.docs.get(instruction).unwrap_or(&NO_DOCS)
+    }
+
+    /// Codes (see `BADGES`) of every badge on any account, e.g. to only list those in a legend.
+    pub fn badge_codes(&self) -> BTreeSet<String> {
+        fn collect(info: &AccountsInfo, codes: &mut BTreeSet<String>) {
+            for field in info.fields.values() {
+                for badge in field.badges() {
+                    codes.insert(badge.split(':').next().unwrap_or_default().to_string());
+                }
+            }
+            for group in info.groups.values() {
+                collect(group, codes);
+            }
+        }
+        let mut codes = BTreeSet::new();
+        for info in self.instructions.values() {
+            collect(info, &mut codes);
+        }
+        codes
+    }
+}
+
+/// Composite groups can't nest deeper than this; it only guards against cyclic structs.
+const MAX_GROUP_DEPTH: usize = 16;
+
+/// A module of the program crate, with the file it's in.
+struct Module {
+    /// Path from the crate root, e.g. `crate::instructions::deposit`
+    path: String,
+    file: PathBuf,
+    items: Vec<syn::Item>,
+}
+
+impl Module {
+    /// The modules of the crate at `lib`, root first. `mod name;` is looked up as `name.rs` or
+    /// `name/mod.rs`; modules whose file is missing or doesn't parse are left out.
+    fn parse_crate(lib: &Path) -> Result<Vec<Module>> {
+        let file = syn::parse_file(&std::fs::read_to_string(lib)?)?;
+        let dir = lib.parent().unwrap_or_else(|| Path::new("."));
+        let mut modules = vec![];
+        Module::collect("crate".to_string(), lib, dir, file.items, &mut modules);
+        Ok(modules)
+    }
+
+    /// Adds the module at `path` with `items`, declared in `file`, and its submodules, whose
+    /// files are under `dir`.
+    fn collect(
+        path: String,
+        file: &Path,
+        dir: &Path,
+        items: Vec<syn::Item>,
+        modules: &mut Vec<Module>,
+    ) {
+        let index = modules.len();
+        modules.push(Module {
+            path: path.clone(),
+            file: file.to_path_buf(),
+            items: vec![],
+        });
+        for item in &items {
+            let item_mod = match item {
+                syn::Item::Mod(item_mod) => item_mod,
+                _ => continue,
+            };
+            let name = item_mod.ident.to_string();
+            let sub_path = format!("{}::{}", path, name);
+            match &item_mod.content {
+                Some((_, sub_items)) => {
+                    let sub_items = sub_items.clone();
+                    Module::collect(sub_path, file, &dir.join(&name), sub_items, modules);
+                }
+                None => {
+                    let candidates = [
+                        (dir.join(format!("{}.rs", name)), dir.join(&name)),
+                        (dir.join(&name).join("mod.rs"), dir.join(&name)),
+                    ];
+                    let parsed = candidates.into_iter().find_map(|(sub_file, sub_dir)| {
+                        let content = std::fs::read_to_string(&sub_file).ok()?;
+                        Some((syn::parse_file(&content).ok()?, sub_file, sub_dir))
+                    });
+                    if let Some((parsed, sub_file, sub_dir)) = parsed {
+                        Module::collect(sub_path, &sub_file, &sub_dir, parsed.items, modules);
+                    }
+                }
+            }
+        }
+        modules[index].items = items;
+    }
+}
+
+/// An accounts struct as anchor parsed it, with where it was declared.
+struct AccountsStructSource<'a> {
+    parsed: AccountsStruct,
+    strct: &'a syn::ItemStruct,
+    module: &'a Module,
+}
+
+/// The `#[derive(Accounts)]` structs of a crate, keyed by their path, e.g.
+/// `crate::instructions::Deposit`, so that structs of the same name in different modules are
+/// kept apart.
+struct AccountsStructs<'a> {
+    structs: BTreeMap<String, AccountsStructSource<'a>>,
+}
+
+impl<'a> AccountsStructs<'a> {
+    fn new(modules: &'a [Module]) -> Self {
+        let mut structs = BTreeMap::new();
+        for module in modules {
+            for item in &module.items {
+                let strct = match item {
+                    syn::Item::Struct(strct) if derives_accounts(strct) => strct,
+                    _ => continue,
+                };
+                if let Ok(parsed) = accounts_parser::parse(strct) {
+                    let key = format!("{}::{}", module.path, strct.ident);
+                    structs.insert(
+                        key,
+                        AccountsStructSource {
+                            parsed,
+                            strct,
+                            module,
+                        },
+                    );
+                }
+            }
+        }
+        AccountsStructs { structs }
+    }
+
+    /// Key of the struct that the type path `ty` names in module `from`, with the `use` paths
+    /// `uses` of that module (see `use_paths`): the path itself, relative to the crate, `from`
+    /// or an import, or else the only struct whose path ends with it. None if there's no such
+    /// struct, or more than one.
+    fn resolve(&self, ty: &[String], from: &str, uses: &[UsePath]) -> Option<&str> {
+        let key = |path: Vec<String>| {
+            let key = path.join("::");
+            self.structs
+                .get_key_value(&key)
+                .map(|(key, _)| key.as_str())
+        };
+        if let Some(found) = key(absolute_path(from, ty)) {
+            return Some(found);
+        }
+        for use_path in uses {
+            let path = match use_path {
+                UsePath::Glob(prefix) => prefix.iter().chain(ty).cloned().collect(),
+                UsePath::Item(path, alias) if *alias == ty[0] => {
+                    path.iter().chain(&ty[1..]).cloned().collect()
+                }
+                UsePath::Item(..) => continue,
+            };
+            if let Some(found) = key(path) {
+                return Some(found);
+            }
+        }
+        let suffix = format!("::{}", ty.join("::"));
+        let mut candidates = self.structs.keys().filter(|key| key.ends_with(&suffix));
+        match (candidates.next(), candidates.next()) {
+            (Some(found), None) => Some(found),
+            _ => None,
+        }
+    }
+
+    fn accounts_info(&self, key: &str, depth: usize) -> AccountsInfo {
+        let mut info = AccountsInfo::default();
+        let source = match self.structs.get(key) {
+            Some(source) if depth < MAX_GROUP_DEPTH => source,
+            _ => return info,
+        };
+        let module = source.module;
+        for field in &source.parsed.fields {
+            let (ident, raw) = match field {
+                AccountField::Field(field) => (&field.ident, None),
+                AccountField::CompositeField(field) => (&field.ident, Some(&field.raw_field)),
+            };
+            let field_name = ident.to_string().to_mixed_case();
+            if let Some(raw) = raw {
+                let uses = use_paths(&module.path, &module.items);
+                let group = type_path(&raw.ty)
+                    .and_then(|ty| self.resolve(&ty, &module.path, &uses))
+                    .map(|group| self.accounts_info(group, depth + 1))
+                    .unwrap_or_default();
+                info.groups.insert(field_name, group);
+                continue;
+            }
+            let raw = source
+                .strct
+                .fields
+                .iter()
+                .find(|raw| raw.ident.as_ref() == Some(ident));
+            let raw = match raw {
+                Some(raw) => raw,
+                None => continue,
+            };
+            let mut constraints = vec![];
+            for attr in raw
+                .attrs
+                .iter()
+                .filter(|attr| attr.path.is_ident("account"))
+            {
+                if let Ok(tokens) =
+                    attr.parse_args_with(Punctuated::<Constraint, syn::Token![,]>::parse_terminated)
+                {
+                    constraints.extend(tokens);
+                }
+            }
+            let field_info = FieldInfo {
+                constraints,
+                ty: Some(type_label(&raw.ty)),
+                doc: doc_comment(&raw.attrs),
+                doc_lines: doc_lines(&raw.attrs),
+                location: Some(Location::of(&module.file, ident)),
+            };
+            info.fields.insert(field_name, field_info);
+        }
+        info
+    }
+}
+
+/// What a `use` item of a module brings in, with absolute paths (see `absolute_path`).
+enum UsePath {
+    /// `use path::*`
+    Glob(Vec<String>),
+    /// `use path::Name` or `use path::Name as Alias`, with the name it's known by
+    Item(Vec<String>, String),
+}
+
+/// The `use` paths of `items` of the module at `from`.
+fn use_paths(from: &str, items: &[syn::Item]) -> Vec<UsePath> {
+    fn collect(from: &str, prefix: &mut Vec<String>, tree: &syn::UseTree, uses: &mut Vec<UsePath>) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                collect(from, prefix, &path.tree, uses);
+                prefix.pop();
+            }
+            syn::UseTree::Name(name) => {
+                let path: Vec<String> = prefix
+                    .iter()
+                    .cloned()
+                    .chain([name.ident.to_string()])
+                    .collect();
+                uses.push(UsePath::Item(
+                    absolute_path(from, &path),
+                    name.ident.to_string(),
+                ));
+            }
+            syn::UseTree::Rename(rename) => {
+                let path: Vec<String> = prefix
+                    .iter()
+                    .cloned()
+                    .chain([rename.ident.to_string()])
+                    .collect();
+                uses.push(UsePath::Item(
+                    absolute_path(from, &path),
+                    rename.rename.to_string(),
+                ));
+            }
+            syn::UseTree::Glob(_) => uses.push(UsePath::Glob(absolute_path(from, prefix))),
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    collect(from, prefix, tree, uses);
+                }
+            }
+        }
+    }
+    let mut uses = vec![];
+    for item in items {
+        if let syn::Item::Use(item_use) = item {
+            collect(from, &mut vec![], &item_use.tree, &mut uses);
+        }
+    }
+    uses
+}
+
+/// `path` as seen from the module at `from`, made absolute: `crate::..` stays, `self::..` and
+/// `super::..` are resolved and anything else is taken as relative to `from`.
+fn absolute_path(from: &str, path: &[String]) -> Vec<String> {
+    let mut absolute: Vec<String> = from.split("::").map(String::from).collect();
+    let mut segments = path.iter().peekable();
+    match segments.peek().map(|segment| segment.as_str()) {
+        Some("crate") => absolute.clear(),
+        Some("self") => {
+            segments.next();
+        }
+        _ => {}
+    }
+    while segments.peek().map(|segment| segment.as_str()) == Some("super") {
+        segments.next();
+        if absolute.len() > 1 {
+            absolute.pop();
+        }
+    }
+    absolute.extend(segments.cloned());
+    absolute
+}
+
+/// One constraint token of `#[account(...)]`, parsed by anchor (see `ConstraintToken`) for its
+/// value and named after its keyword, e.g. `token::mint`.
+impl Parse for Constraint {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = if input.peek(syn::LitStr) {
+            // deprecated string literal constraint, e.g. `"vault.balance > 0"`
+            "constraint".to_string()
+        } else {
+            let fork = input.fork();
+            let mut name = fork.call(syn::Ident::parse_any)?.to_string();
+            while fork.peek(syn::Token![::]) {
+                fork.parse::<syn::Token![::]>()?;
+                name = format!("{}::{}", name, fork.call(syn::Ident::parse_any)?);
+            }
+            name
+        };
+        let tokens = |value: &dyn ToTokens| Some(value.to_token_stream().to_string());
+        let value = match input.parse::<ConstraintToken>()? {
+            ConstraintToken::HasOne(c) => tokens(&c.join_target),
+            ConstraintToken::Payer(c) => tokens(&c.target),
+            ConstraintToken::Close(c) => tokens(&c.sol_dest),
+            ConstraintToken::Owner(c) => tokens(&c.owner_address),
+            ConstraintToken::Address(c) => tokens(&c.address),
+            ConstraintToken::Raw(c) => tokens(&c.raw),
+            ConstraintToken::Literal(c) => Some(c.lit.value()),
+            ConstraintToken::Space(c) => tokens(&c.space),
+            ConstraintToken::Seeds(c) => tokens(&c.seeds),
+            ConstraintToken::State(c) => tokens(&c.program_target),
+            _ => None,
+        };
+        Ok(Constraint { name, value })
+    }
+}
+
+/// Lines of the `///` (or `#[doc = ".."]`) comments in `attrs`, without the leading space.
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(doc),
+                ..
+            })) => Some(doc.value()),
+            _ => None,
+        })
+        .flat_map(|doc| {
+            doc.lines()
+                .map(|line| {
+                    line.strip_prefix(' ')
+                        .unwrap_or(line)
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Doc comment text of `lines`: the lines of a paragraph joined with spaces, paragraphs with
+/// newlines. None if there is no text.
+fn doc_text<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut paragraphs: Vec<String> = vec![];
+    let mut paragraph = String::new();
+    for line in lines.into_iter().map(str::trim).chain([""]) {
+        if line.is_empty() {
+            if !paragraph.is_empty() {
+                paragraphs.push(std::mem::take(&mut paragraph));
+            }
+        } else {
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(line);
+        }
+    }
+    (!paragraphs.is_empty()).then(|| paragraphs.join("\n"))
+}
+
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    doc_text(doc_lines(attrs).iter().map(String::as_str))
+}
+
+/// Doc comments of an instruction with `attrs`, its `# Arguments` section split off into the
+/// docs of its args (see `InstructionDocs`).
+fn instruction_docs(attrs: &[syn::Attribute]) -> InstructionDocs {
+    let lines = doc_lines(attrs);
+    let mut doc = vec![];
+    let mut args: Vec<(String, Vec<&str>)> = vec![];
+    let mut in_args = false;
+    for line in &lines {
+        if let Some(heading) = line.trim_start().strip_prefix('#') {
+            in_args = heading
+                .trim_start_matches('#')
+                .trim()
+                .eq_ignore_ascii_case("arguments");
+            if in_args {
+                continue;
+            }
+        }
+        if !in_args {
+            doc.push(line.as_str());
+            continue;
+        }
+        // `* `name` - text`, also with `-` bullets and `:` after the name
+        let item = line
+            .trim_start()
+            .strip_prefix(|c| c == '*' || c == '-')
+            .map(str::trim_start);
+        match (item, args.last_mut()) {
+            (Some(item), _) => {
+                let (name, text) = item
+                    .split_once(" - ")
+                    .or_else(|| item.split_once(':'))
+                    .unwrap_or((item, ""));
+                let name = name.trim().trim_matches('`').to_mixed_case();
+                args.push((name, vec![text]));
+            }
+            (None, Some((_, text))) => text.push(line),
+            (None, None) => {}
+        }
+    }
+    InstructionDocs {
+        doc: doc_text(doc),
+        args: args
+            .into_iter()
+            .filter_map(|(name, text)| Some((name, doc_text(text)?)))
+            .collect(),
+    }
+}
+
+fn has_attr(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path
+            .segments
+            .last()
+            .map(|s| s.ident == name)
+            .unwrap_or(false)
+    })
+}
+
+/// Whether `strct` has `#[derive(.., Accounts, ..)]` (also as `anchor_lang::Accounts`).
+fn derives_accounts(strct: &syn::ItemStruct) -> bool {
+    strct
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .any(|nested| match nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) => path
+                .segments
+                .last()
+                .map(|s| s.ident == "Accounts")
+                .unwrap_or(false),
+            _ => false,
+        })
+}
+
+/// Path of the accounts struct `T` of an instruction taking `ctx: Context<T>` first, e.g.
+/// `["instructions", "Deposit"]`.
+fn context_accounts(method: &syn::ItemFn) -> Option<Vec<String>> {
+    match method.sig.inputs.first()? {
+        syn::FnArg::Typed(arg) => {
+            let segment = match &*arg.ty {
+                syn::Type::Path(ty) => ty.path.segments.last()?,
+                _ => return None,
+            };
+            match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => {
+                    args.args.iter().rev().find_map(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => type_path(ty),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            }
+        }
+        syn::FnArg::Receiver(_) => None,
+    }
+}
+
+/// Path segments of a type without its arguments, e.g. `["TokenTransferCtx"]` for
+/// `TokenTransferCtx<'info>`.
+fn type_path(ty: &syn::Type) -> Option<Vec<String>> {
+    match ty {
+        syn::Type::Path(ty) => Some(
+            ty.path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Short form of a type, keeping only the last path segment and type arguments, e.g.
+/// `Account<Vault>` for `anchor_lang::accounts::account::Account<'info, Vault>`.
+fn type_label(ty: &syn::Type) -> String {
+    let segment = match ty {
+        syn::Type::Path(ty) => ty.path.segments.last(),
+        _ => None,
+    };
+    let segment = match segment {
+        Some(segment) => segment,
+        None => return ty.to_token_stream().to_string(),
+    };
+    let args: Vec<String> = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(type_label(ty)),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    if args.is_empty() {
+        segment.ident.to_string()
+    } else {
+        format!("{}<{}>", segment.ident, args.join(", "))
+    }
+}
@@ -0,0 +1,352 @@
+use crate::diff::Status;
+use crate::error::VizError;
+use crate::layout::ROLE_LINE_PX_HEIGHT;
+use crate::viz::Role;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::path::Path;
+
+/// Names of the built-in themes, `light` being the default.
+pub const BUILTIN_THEMES: [&str; 3] = ["light", "dark", "colorblind-safe"];
+
+/// An `#rrggbb` color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    /// This color as a `#rrggbb` hex string
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid color {:?}, expected #rrggbb", hex);
+        let digits = hex
+            .strip_prefix('#')
+            .filter(|digits| digits.len() == 6 && digits.is_ascii());
+        let digits = digits.ok_or_else(invalid)?;
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Color(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.hex()
+    }
+}
+
+/// Colors of a theme: the figure itself, then the fill of boxes per role.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Colors {
+    pub background: Color,
+    /// Title, labels, separators, borders and the fill of badges (with `background` text)
+    pub foreground: Color,
+    /// Text on boxes and type cards
+    pub text: Color,
+    pub instruction: Color,
+    pub signer: Color,
+    #[serde(rename = "mut")]
+    pub mutable: Color,
+    pub immut: Color,
+    pub arg: Color,
+    /// Border and badge of accounts that anchor doesn't check (UncheckedAccount, AccountInfo)
+    pub unchecked: Color,
+    /// Fill of the account struct and custom type cards, and of unchanged boxes in a diff
+    pub card: Color,
+    /// Fill of boxes in a diff, by how they changed
+    pub added: Color,
+    pub removed: Color,
+    pub changed: Color,
+}
+
+/// Font family and sizes of a theme.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Font {
+    /// Font family, e.g. `monospace` or `DejaVu Sans Mono`
+    pub family: String,
+    pub title: u32,
+    /// Box labels and names
+    pub text: u32,
+    pub badge: u32,
+    /// Small text: account types, seeds, type card lines, the legend
+    pub small: u32,
+}
+
+/// Pixel sizes of a theme.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sizes {
+    pub box_width: usize,
+    /// Height of a box's label and name; boxes are stretched below it for types, badges and seeds
+    pub box_height: usize,
+    /// Gap between boxes, and their padding
+    pub spacing: usize,
+    /// Height of the title header
+    pub header: usize,
+    /// Width of the lines between columns and above the types
+    pub separator: usize,
+}
+
+/// Colors, font and sizes of a visualization, as read from a theme file:
+///
+/// ```toml
+/// theme = "dark"   # built-in theme to start from, light by default
+///
+/// [colors]
+/// mut = "#ff6464"
+///
+/// [font]
+/// family = "DejaVu Sans Mono"
+///
+/// [sizes]
+/// box_width = 280
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub colors: Colors,
+    pub font: Font,
+    pub sizes: Sizes,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            colors: Colors {
+                background: Color(255, 255, 255),
+                foreground: Color(0, 0, 0),
+                text: Color(0, 0, 0),
+                instruction: Color(255, 200, 200),
+                signer: Color(0, 255, 163),
+                mutable: Color(255, 100, 100),
+                immut: Color(3, 225, 255),
+                arg: Color(220, 31, 255),
+                unchecked: Color(255, 165, 0),
+                card: Color(235, 235, 235),
+                added: Color(130, 220, 130),
+                removed: Color(255, 140, 140),
+                changed: Color(255, 210, 90),
+            },
+            font: Font {
+                family: "monospace".to_string(),
+                title: 24,
+                text: 20,
+                badge: 11,
+                small: 14,
+            },
+            sizes: Sizes {
+                box_width: 240,
+                box_height: 60,
+                spacing: 8,
+                header: 100,
+                separator: 2,
+            },
+        }
+    }
+}
+
+impl Theme {
+    /// The built-in theme called `name` (see `BUILTIN_THEMES`), if any.
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let light = Theme::default();
+        match name {
+            "light" => Some(light),
+            "dark" => Some(Theme {
+                colors: Colors {
+                    background: Color(30, 30, 30),
+                    foreground: Color(230, 230, 230),
+                    text: Color(245, 245, 245),
+                    instruction: Color(122, 59, 59),
+                    signer: Color(24, 120, 84),
+                    mutable: Color(163, 48, 48),
+                    immut: Color(22, 104, 140),
+                    arg: Color(118, 40, 140),
+                    unchecked: Color(214, 110, 0),
+                    card: Color(50, 50, 50),
+                    added: Color(40, 110, 50),
+                    removed: Color(140, 45, 45),
+                    changed: Color(135, 105, 20),
+                },
+                ..light
+            }),
+            // Okabe-Ito palette, told apart with any kind of color vision deficiency
+            "colorblind-safe" => Some(Theme {
+                colors: Colors {
+                    instruction: Color(230, 159, 0),
+                    signer: Color(0, 158, 115),
+                    mutable: Color(213, 94, 0),
+                    immut: Color(86, 180, 233),
+                    arg: Color(204, 121, 167),
+                    unchecked: Color(240, 228, 66),
+                    added: Color(0, 158, 115),
+                    removed: Color(213, 94, 0),
+                    changed: Color(240, 228, 66),
+                    ..light.colors
+                },
+                ..light
+            }),
+            _ => None,
+        }
+    }
+
+    /// The theme `spec`: a built-in theme name, or else the path of a theme file.
+    pub fn load(spec: &str) -> Result<Theme> {
+        if let Some(theme) = Theme::builtin(spec) {
+            return Ok(theme);
+        }
+        let path = shellexpand::tilde(spec);
+        let text = std::fs::read_to_string(&*path).map_err(|err| {
+            invalid(
+                spec,
+                format!(
+                    "not a built-in theme ({}) nor a readable file: {}",
+                    BUILTIN_THEMES.join(", "),
+                    err
+                ),
+            )
+        })?;
+        let overrides: toml::Value = text.parse().map_err(|err| invalid(spec, err))?;
+        Theme::from_toml(overrides, None).map_err(|err| invalid(spec, err).into())
+    }
+
+    /// The theme of the anchor workspace at `dir` (or a parent of it): the `[viz]` section of its
+    /// Anchor.toml, which is a theme whose `theme` key may also name a theme file (relative to
+    /// Anchor.toml). The default theme if there is no `[viz]` section.
+    pub fn from_workspace(dir: &Path) -> Result<Theme> {
+        let anchor_toml = match dir
+            .ancestors()
+            .map(|dir| dir.join("Anchor.toml"))
+            .find(|f| f.exists())
+        {
+            Some(anchor_toml) => anchor_toml,
+            None => return Ok(Theme::default()),
+        };
+        let spec = format!("[viz] of {}", anchor_toml.display());
+        let config: toml::Value = std::fs::read_to_string(&anchor_toml)?
+            .parse()
+            .map_err(|err| invalid(&spec, err))?;
+        match config.get("viz") {
+            Some(viz) => Theme::from_toml(viz.clone(), anchor_toml.parent())
+                .map_err(|err| invalid(&spec, err).into()),
+            None => Ok(Theme::default()),
+        }
+    }
+
+    /// The theme given on the command line, if any, or else the one of the workspace at `dir`.
+    pub fn resolve(spec: Option<&str>, dir: &Path) -> Result<Theme> {
+        match spec {
+            Some(spec) => Theme::load(spec),
+            None => Theme::from_workspace(dir),
+        }
+    }
+
+    /// Fill color of boxes with `role`.
+    pub fn color(&self, role: Role) -> Color {
+        match role {
+            Role::Instruction => self.colors.instruction,
+            Role::Signer => self.colors.signer,
+            Role::Mut => self.colors.mutable,
+            Role::Immut => self.colors.immut,
+            Role::Arg => self.colors.arg,
+        }
+    }
+
+    /// Fill color of boxes with `status` in a diff.
+    pub fn status_color(&self, status: Status) -> Color {
+        match status {
+            Status::Unchanged => self.colors.card,
+            Status::Added => self.colors.added,
+            Status::Removed => self.colors.removed,
+            Status::Changed => self.colors.changed,
+        }
+    }
+
+    /// Theme from toml `overrides` of the theme named by their `theme` key (light by default):
+    /// a built-in theme or, with a `dir` to resolve it against, a theme file.
+    fn from_toml(mut overrides: toml::Value, dir: Option<&Path>) -> Result<Theme, String> {
+        let table = overrides.as_table_mut().ok_or("expected a table")?;
+        let base = match table.remove("theme") {
+            None => Theme::default(),
+            Some(toml::Value::String(name)) => match (Theme::builtin(&name), dir) {
+                (Some(theme), _) => theme,
+                (None, Some(dir)) => {
+                    let path = dir.join(&*shellexpand::tilde(&name));
+                    Theme::load(&path.to_string_lossy()).map_err(|err| format!("{:#}", err))?
+                }
+                (None, None) => {
+                    return Err(format!(
+                        "theme {:?} is not a built-in theme ({})",
+                        name,
+                        BUILTIN_THEMES.join(", ")
+                    ))
+                }
+            },
+            Some(_) => return Err("theme must be a string".to_string()),
+        };
+
+        let mut merged = toml::Value::try_from(&base).map_err(|err| err.to_string())?;
+        merge(&mut merged, overrides);
+        let theme: Theme = merged.try_into().map_err(|err| err.to_string())?;
+        theme.validate()?;
+        Ok(theme)
+    }
+
+    /// Checks that the sizes leave room for text, so layouts don't come out degenerate.
+    fn validate(&self) -> Result<(), String> {
+        let (font, sizes) = (&self.font, &self.sizes);
+        if [font.title, font.text, font.badge, font.small].contains(&0) {
+            return Err("font sizes must be at least 1".to_string());
+        }
+        if sizes.spacing == 0 {
+            return Err("sizes.spacing must be at least 1".to_string());
+        }
+        if sizes.box_width <= 4 * sizes.spacing || sizes.box_height <= 2 * sizes.spacing {
+            return Err(
+                "sizes.box_width must be over 4 and sizes.box_height over 2 times sizes.spacing"
+                    .to_string(),
+            );
+        }
+        // the title, then the color legend on a line of its own above the bottom spacing
+        let min_header = sizes.spacing + ROLE_LINE_PX_HEIGHT + font.title as usize;
+        if sizes.header < min_header {
+            return Err(format!(
+                "sizes.header must be at least {} (sizes.spacing + {} + font.title)",
+                min_header, ROLE_LINE_PX_HEIGHT
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Recursively overrides the tables of `base` with those of `overrides`.
+fn merge(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base) => merge(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+fn invalid(spec: &str, err: impl std::fmt::Display) -> VizError {
+    VizError::InvalidTheme {
+        theme: spec.to_string(),
+        message: err.to_string(),
+    }
+}
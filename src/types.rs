@@ -0,0 +1,448 @@
+use crate::source::AccountsInfo;
+use crate::viz::Program;
+use anchor_syn::idl::{EnumFields, Idl, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy};
+use serde::Serialize;
+use std::fmt;
+
+/// Bytes of the discriminator anchor prepends to the data of every `#[account]`.
+pub const DISCRIMINATOR_SIZE: usize = 8;
+
+/// Defined types can't nest deeper than this; it only guards against cyclic idls.
+const MAX_TYPE_DEPTH: usize = 16;
+
+/// Borsh serialized size of a type's largest value, i.e. with `Option`s set and enums
+/// at their largest variant, which is what an account's `space` has to fit.
+///
+/// `variable` types hold a `Vec`, `String` or `Bytes`, or a type missing from the idl;
+/// `bytes` is then only a lower bound (with every `Vec`, `String` and `Bytes` empty).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BorshSize {
+    pub bytes: usize,
+    pub variable: bool,
+}
+
+impl BorshSize {
+    fn fixed(bytes: usize) -> Self {
+        BorshSize {
+            bytes,
+            variable: false,
+        }
+    }
+
+    fn variable(bytes: usize) -> Self {
+        BorshSize {
+            bytes,
+            variable: true,
+        }
+    }
+
+    fn plus(self, other: BorshSize) -> Self {
+        BorshSize {
+            bytes: self.bytes + other.bytes,
+            variable: self.variable || other.variable,
+        }
+    }
+
+    fn max(self, other: BorshSize) -> Self {
+        BorshSize {
+            bytes: self.bytes.max(other.bytes),
+            variable: self.variable || other.variable,
+        }
+    }
+}
+
+// `48 bytes`, or `4+ bytes` for variable sizes
+impl fmt::Display for BorshSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plus = if self.variable { "+" } else { "" };
+        let unit = if self.bytes == 1 && !self.variable {
+            "byte"
+        } else {
+            "bytes"
+        };
+        write!(f, "{}{} {}", self.bytes, plus, unit)
+    }
+}
+
+/// What a `TypeDef` was declared as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeKind {
+    /// An `#[account]` struct, from the idl's `accounts`
+    Account,
+    /// A struct from the idl's `types`
+    Struct,
+    /// An enum from the idl's `types`
+    Enum,
+}
+
+impl TypeKind {
+    /// Label of the kind, e.g. to head a type's box
+    pub fn label(&self) -> &'static str {
+        match self {
+            TypeKind::Account => "Account:",
+            TypeKind::Struct => "Struct:",
+            TypeKind::Enum => "Enum:",
+        }
+    }
+}
+
+/// A named field of a struct or enum variant, or a positional one (named `0`, `1`, ..).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TypeField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+    pub size: BorshSize,
+}
+
+/// A variant of an enum with its fields, if any.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TypeVariant {
+    pub name: String,
+    pub fields: Vec<TypeField>,
+}
+
+/// An account struct or custom type of a program, with its layout and where it's used.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TypeDef {
+    pub kind: TypeKind,
+    pub name: String,
+    /// Size of the serialized type; for accounts including the discriminator
+    pub size: BorshSize,
+    /// Fields of a struct (or account)
+    pub fields: Vec<TypeField>,
+    /// Variants of an enum
+    pub variants: Vec<TypeVariant>,
+    /// Where the type is referenced, e.g. `open.vault` (an account declared as
+    /// `Account<Vault>`), `open.kind` (an arg) or `Vault.kind` (a field of another type)
+    pub used_by: Vec<String>,
+}
+
+/// The `#[account]` structs of `program` followed by its custom types, as declared in its idl.
+/// Accounts reference their struct through their declared type, which is only known from the
+/// program source.
+pub fn type_defs(program: &Program) -> Vec<TypeDef> {
+    let idl = &program.idl;
+    let accounts = idl.accounts.iter().map(|def| (true, def));
+    let types = idl.types.iter().map(|def| (false, def));
+    accounts
+        .chain(types)
+        .map(|(is_account, def)| {
+            let mut type_def = type_def(def, idl);
+            if is_account {
+                type_def.kind = TypeKind::Account;
+                type_def.size = BorshSize::fixed(DISCRIMINATOR_SIZE).plus(type_def.size);
+            }
+            type_def.used_by = used_by(&def.name, program);
+            type_def
+        })
+        .collect()
+}
+
+fn type_def(def: &IdlTypeDefinition, idl: &Idl) -> TypeDef {
+    let (kind, fields, variants) = match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            (TypeKind::Struct, type_fields(fields, idl), vec![])
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let variants = variants
+                .iter()
+                .map(|variant| TypeVariant {
+                    name: variant.name.clone(),
+                    fields: match &variant.fields {
+                        Some(EnumFields::Named(fields)) => type_fields(fields, idl),
+                        Some(EnumFields::Tuple(types)) => types
+                            .iter()
+                            .enumerate()
+                            .map(|(i, ty)| TypeField {
+                                name: i.to_string(),
+                                ty: ty.clone(),
+                                size: borsh_size(ty, idl),
+                            })
+                            .collect(),
+                        None => vec![],
+                    },
+                })
+                .collect();
+            (TypeKind::Enum, vec![], variants)
+        }
+    };
+    TypeDef {
+        kind,
+        name: def.name.clone(),
+        size: definition_size(def, idl, 0),
+        fields,
+        variants,
+        used_by: vec![],
+    }
+}
+
+fn type_fields(fields: &[IdlField], idl: &Idl) -> Vec<TypeField> {
+    fields
+        .iter()
+        .map(|field| TypeField {
+            name: field.name.clone(),
+            ty: field.ty.clone(),
+            size: borsh_size(&field.ty, idl),
+        })
+        .collect()
+}
+
+/// Borsh serialized size of a value of type `ty` (see `BorshSize`), looking up defined types
+/// in the `types` and `accounts` of `idl`.
+pub fn borsh_size(ty: &IdlType, idl: &Idl) -> BorshSize {
+    size(ty, idl, 0)
+}
+
+fn size(ty: &IdlType, idl: &Idl, depth: usize) -> BorshSize {
+    match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 => BorshSize::fixed(1),
+        IdlType::U16 | IdlType::I16 => BorshSize::fixed(2),
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => BorshSize::fixed(4),
+        IdlType::U64 | IdlType::I64 | IdlType::F64 => BorshSize::fixed(8),
+        IdlType::U128 | IdlType::I128 => BorshSize::fixed(16),
+        IdlType::PublicKey => BorshSize::fixed(32),
+        // u32 length prefix, then the content
+        IdlType::Bytes | IdlType::String | IdlType::Vec(_) => BorshSize::variable(4),
+        IdlType::Option(inner) => BorshSize::fixed(1).plus(size(inner, idl, depth)),
+        IdlType::Array(inner, len) => {
+            let inner = size(inner, idl, depth);
+            BorshSize {
+                bytes: inner.bytes * len,
+                variable: inner.variable,
+            }
+        }
+        IdlType::Defined(name) => {
+            let def = idl
+                .types
+                .iter()
+                .chain(idl.accounts.iter())
+                .find(|def| def.name == *name);
+            match def {
+                Some(def) if depth < MAX_TYPE_DEPTH => definition_size(def, idl, depth + 1),
+                _ => BorshSize::variable(0),
+            }
+        }
+    }
+}
+
+fn definition_size(def: &IdlTypeDefinition, idl: &Idl, depth: usize) -> BorshSize {
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            total_size(fields.iter().map(|field| &field.ty), idl, depth)
+        }
+        // u8 variant index, then the largest variant
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let largest = variants
+                .iter()
+                .map(|variant| match &variant.fields {
+                    Some(EnumFields::Named(fields)) => {
+                        total_size(fields.iter().map(|field| &field.ty), idl, depth)
+                    }
+                    Some(EnumFields::Tuple(types)) => total_size(types.iter(), idl, depth),
+                    None => BorshSize::default(),
+                })
+                .fold(BorshSize::default(), BorshSize::max);
+            BorshSize::fixed(1).plus(largest)
+        }
+    }
+}
+
+fn total_size<'a>(types: impl Iterator<Item = &'a IdlType>, idl: &Idl, depth: usize) -> BorshSize {
+    types.fold(BorshSize::default(), |total, ty| {
+        total.plus(size(ty, idl, depth))
+    })
+}
+
+/// How a value of type `ty` is Borsh serialized: its `type_label` with defined types expanded
+/// (from the `types` and `accounts` of `idl`) and names left out, e.g. `Option<(u8, enum{() |
+/// (u64)})>`. Types with the same layout are serialized alike.
+pub fn borsh_layout(ty: &IdlType, idl: &Idl) -> String {
+    layout(ty, idl, 0)
+}
+
+/// `borsh_layout` of the defined type `def`.
+pub fn definition_layout(def: &IdlTypeDefinition, idl: &Idl) -> String {
+    definition_layout_at(def, idl, 0)
+}
+
+fn layout(ty: &IdlType, idl: &Idl, depth: usize) -> String {
+    match ty {
+        IdlType::Option(inner) => format!("Option<{}>", layout(inner, idl, depth)),
+        IdlType::Vec(inner) => format!("Vec<{}>", layout(inner, idl, depth)),
+        IdlType::Array(inner, len) => format!("[{}; {}]", layout(inner, idl, depth), len),
+        IdlType::Defined(name) => {
+            let def = idl
+                .types
+                .iter()
+                .chain(idl.accounts.iter())
+                .find(|def| def.name == *name);
+            match def {
+                Some(def) if depth < MAX_TYPE_DEPTH => definition_layout_at(def, idl, depth + 1),
+                _ => name.clone(),
+            }
+        }
+        _ => type_label(ty),
+    }
+}
+
+fn definition_layout_at(def: &IdlTypeDefinition, idl: &Idl, depth: usize) -> String {
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            tuple_layout(fields.iter().map(|field| &field.ty), idl, depth)
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let variants: Vec<String> = variants
+                .iter()
+                .map(|variant| match &variant.fields {
+                    Some(EnumFields::Named(fields)) => {
+                        tuple_layout(fields.iter().map(|field| &field.ty), idl, depth)
+                    }
+                    Some(EnumFields::Tuple(types)) => tuple_layout(types.iter(), idl, depth),
+                    None => "()".to_string(),
+                })
+                .collect();
+            format!("enum{{{}}}", variants.join(" | "))
+        }
+    }
+}
+
+fn tuple_layout<'a>(types: impl Iterator<Item = &'a IdlType>, idl: &Idl, depth: usize) -> String {
+    let types: Vec<String> = types.map(|ty| layout(ty, idl, depth)).collect();
+    format!("({})", types.join(", "))
+}
+
+/// Rust-like name of an idl type, e.g. `Option<Vec<MyStruct>>`, `[u8; 32]` or `Pubkey`.
+pub fn type_label(ty: &IdlType) -> String {
+    nested_type_label(ty, usize::MAX)
+}
+
+/// `type_label` of `ty`, abbreviated to at most `max_chars` characters: type arguments are
+/// elided from the innermost out (`Option<Vec<…>>`, then `Option<…>`), and if the outermost
+/// type alone is still too long it's cut short with an ellipsis.
+pub fn abbreviated_type_label(ty: &IdlType, max_chars: usize) -> String {
+    let depth = type_depth(ty);
+    for depth in (0..=depth).rev() {
+        let label = nested_type_label(ty, depth);
+        if label.chars().count() <= max_chars {
+            return label;
+        }
+    }
+    let label = nested_type_label(ty, 0);
+    let kept: String = label.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", kept)
+}
+
+/// Label of `ty` with type arguments more than `depth` levels down elided as `…`.
+fn nested_type_label(ty: &IdlType, depth: usize) -> String {
+    let inner_label = |inner: &IdlType| match depth {
+        0 => "…".to_string(),
+        depth => nested_type_label(inner, depth - 1),
+    };
+    match ty {
+        IdlType::PublicKey => "Pubkey".to_string(),
+        IdlType::String => "String".to_string(),
+        IdlType::Bytes => "Vec<u8>".to_string(),
+        IdlType::Defined(name) => name.clone(),
+        IdlType::Option(inner) => format!("Option<{}>", inner_label(inner)),
+        IdlType::Vec(inner) => format!("Vec<{}>", inner_label(inner)),
+        IdlType::Array(inner, len) => format!("[{}; {}]", inner_label(inner), len),
+        // the other types are primitives, which the idl names as rust does (`u64`, `bool`, ..)
+        primitive => serde_json::to_value(primitive)
+            .ok()
+            .and_then(|name| name.as_str().map(String::from))
+            .unwrap_or_default(),
+    }
+}
+
+/// Levels of type arguments in `ty`, e.g. 2 for `Option<Vec<u8>>`.
+fn type_depth(ty: &IdlType) -> usize {
+    match ty {
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            1 + type_depth(inner)
+        }
+        _ => 0,
+    }
+}
+
+/// Names of the defined types that `ty` refers to, e.g. `MyStruct` for `Option<Vec<MyStruct>>`.
+pub fn defined_types(ty: &IdlType) -> Vec<&str> {
+    match ty {
+        IdlType::Defined(name) => vec![name.as_str()],
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            defined_types(inner)
+        }
+        _ => vec![],
+    }
+}
+
+/// Whether the declared type of an account, e.g. `Box<Account<Vault>>`, refers to type `name`.
+pub fn declares_type(declared: &str, name: &str) -> bool {
+    declared
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|ident| ident == name)
+}
+
+/// Instruction args, accounts and fields of other types that refer to the type `name`.
+fn used_by(name: &str, program: &Program) -> Vec<String> {
+    let idl = &program.idl;
+    let mut used_by = vec![];
+    for ix in &idl.instructions {
+        collect_accounts(
+            name,
+            &ix.name,
+            program.source.accounts(&ix.name),
+            &mut used_by,
+        );
+        for arg in &ix.args {
+            if defined_types(&arg.ty).contains(&name) {
+                used_by.push(format!("{}.{}", ix.name, arg.name));
+            }
+        }
+    }
+    for def in idl.accounts.iter().chain(idl.types.iter()) {
+        let types: Vec<(&str, &IdlType)> = match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => fields
+                .iter()
+                .map(|field| (field.name.as_str(), &field.ty))
+                .collect(),
+            IdlTypeDefinitionTy::Enum { variants } => variants
+                .iter()
+                .flat_map(|variant| match &variant.fields {
+                    Some(EnumFields::Named(fields)) => fields
+                        .iter()
+                        .map(|field| (variant.name.as_str(), &field.ty))
+                        .collect(),
+                    Some(EnumFields::Tuple(types)) => {
+                        types.iter().map(|ty| (variant.name.as_str(), ty)).collect()
+                    }
+                    None => vec![],
+                })
+                .collect(),
+        };
+        for (field, ty) in types {
+            let path = format!("{}.{}", def.name, field);
+            if defined_types(ty).contains(&name) && !used_by.contains(&path) {
+                used_by.push(path);
+            }
+        }
+    }
+    used_by
+}
+
+fn collect_accounts(name: &str, path: &str, info: &AccountsInfo, used_by: &mut Vec<String>) {
+    for (account, field) in &info.fields {
+        if field
+            .ty
+            .as_deref()
+            .map(|ty| declares_type(ty, name))
+            .unwrap_or(false)
+        {
+            used_by.push(format!("{}.{}", path, account));
+        }
+    }
+    for (group, info) in &info.groups {
+        collect_accounts(name, &format!("{}.{}", path, group), info, used_by);
+    }
+}
@@ -1,13 +1,241 @@
-use anchor_syn::idl::{Idl, IdlAccount, IdlAccountItem, IdlAccounts};
+use anchor_syn::idl::Idl;
+use crate::compat;
+use crate::diff::{self, ProgramDiff};
+use crate::dot;
+use crate::error::VizError;
+use crate::git;
+use crate::html;
+use crate::layout::{self, Align, Badge, Group, Layout, LayoutBox, Line, Rect, TypeCard};
+use crate::lint;
+use crate::matrix::{self, MatrixLayout};
+use crate::mermaid;
+use crate::source::SourceInfo;
+use crate::theme::{Color, Theme};
 use anyhow::{Result, anyhow};
+use clap::ArgEnum;
+use plotters::coord::Shift;
 use plotters::prelude::*;
 use plotters::style::text_anchor::Pos;
 use plotters::style::ShapeStyle;
 use plotters_backend::text_anchor::{HPos, VPos};
 use plotters_backend::{BackendColor, FontStyle};
+use serde::Serialize;
 use std::convert::TryInto;
+use std::io::Read;
+use std::num::NonZeroUsize;
 use std::path::{PathBuf, Path};
- 
+
+// new anchor-cli feature as of 0.22.0
+const SKIP_LINT: bool = true;
+
+// border of accounts that anchor doesn't check (UncheckedAccount, AccountInfo)
+const UNCHECKED_BORDER_PX_WIDTH: usize = 3;
+
+/// Output formats for the visualization.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Raster image via plotters' BitMapBackend
+    Png,
+    /// Scalable vector image via plotters' SVGBackend
+    Svg,
+    /// Self-contained interactive html explorer
+    Html,
+    /// Mermaid flowchart, e.g. for markdown docs
+    Mermaid,
+    /// Graphviz graph of instructions and the accounts they share, to lay out with dot/neato
+    Dot,
+    /// The positioned boxes, lines and text of the png/svg as json, for custom renderers
+    LayoutJson,
+    /// Account usage matrix (instructions × accounts) as csv, e.g. for spreadsheets
+    Csv,
+}
+
+impl Format {
+    /// File extension matching this output format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+            Format::Html => "html",
+            Format::Mermaid => "mmd",
+            Format::Dot => "dot",
+            Format::LayoutJson => "layout.json",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+/// How the png and svg (and the html explorer) handle text too long for its box.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fit {
+    /// Shrink the text, then wrap it, then cut it short with an ellipsis
+    Shrink,
+    /// Widen all boxes to fit the widest name
+    Widen,
+}
+
+/// What a box in the visualization stands for. Every output colors boxes by role (see `Theme`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Instruction,
+    Signer,
+    Mut,
+    Immut,
+    Arg,
+}
+
+impl Role {
+    pub const ALL: [Role; 5] = [
+        Role::Instruction,
+        Role::Signer,
+        Role::Mut,
+        Role::Immut,
+        Role::Arg,
+    ];
+
+    /// What boxes with this role stand for, e.g. for a legend
+    pub fn description(&self) -> &'static str {
+        match self {
+            Role::Instruction => "instruction",
+            Role::Signer => "signer",
+            Role::Mut => "mutable account",
+            Role::Immut => "read-only account",
+            Role::Arg => "argument",
+        }
+    }
+
+    /// Short name of this role, e.g. for css classes
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Instruction => "instruction",
+            Role::Signer => "signer",
+            Role::Mut => "mut",
+            Role::Immut => "immut",
+            Role::Arg => "arg",
+        }
+    }
+}
+
+/// Default output filename template: `<program_name>.<format>`, in the current dir.
+pub const DEFAULT_OUT_TEMPLATE: &str = "{name}.{format}";
+
+/// Default output filename template of a diff: `<program_name>-diff.<format>`, in the current dir.
+pub const DEFAULT_DIFF_OUT_TEMPLATE: &str = "{name}-diff.{format}";
+
+/// Default output filename template of a lint: `<program_name>-lint.<format>`.
+pub const DEFAULT_LINT_OUT_TEMPLATE: &str = "{name}-lint.{format}";
+
+/// Default output filename template of an account matrix: `<program_name>-matrix.<format>`.
+pub const DEFAULT_MATRIX_OUT_TEMPLATE: &str = "{name}-matrix.{format}";
+
+/// Default number of accounts, arguments per instruction column.
+const DEFAULT_WIDTH: NonZeroUsize = match NonZeroUsize::new(2) {
+    Some(width) => width,
+    None => panic!("the default width is 0"),
+};
+
+/// How a visualization is drawn (see `render`). The default is a png with 2 accounts and args
+/// per column, without seeds or footnotes, text shrunk to fit its boxes, in the default theme.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub format: Format,
+    /// Number of accounts, arguments per instruction column (see `with_width`)
+    pub width: NonZeroUsize,
+    /// Whether the seeds of PDA accounts are listed on their boxes (png, svg), and the program
+    /// is parsed with the seeds feature to have them
+    pub seeds: bool,
+    /// Whether doc comments are listed under each column as numbered footnotes (png, svg)
+    pub footnotes: bool,
+    /// How text too long for its box is handled
+    pub fit: Fit,
+    /// Colors, font and sizes
+    pub theme: Theme,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions::new(Format::Png)
+    }
+}
+
+impl RenderOptions {
+    /// The default options (see `RenderOptions`) with the given format.
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            width: DEFAULT_WIDTH,
+            seeds: false,
+            footnotes: false,
+            fit: Fit::Shrink,
+            theme: Theme::default(),
+        }
+    }
+
+    /// These options with `width` accounts and args per column, which fails with
+    /// `VizError::InvalidWidth` if it's 0.
+    pub fn with_width(self, width: usize) -> Result<Self> {
+        let width = NonZeroUsize::new(width).ok_or(VizError::InvalidWidth(width))?;
+        Ok(Self { width, ..self })
+    }
+}
+
+/// Where and how a visualization is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Output {
+    /// Output path template. The placeholders `{name}` (program name), `{version}` (idl version)
+    /// and `{format}` (file extension) are filled in per program.
+    pub template: String,
+    /// Directory that a relative output path is resolved against (empty for the current dir)
+    pub dir: PathBuf,
+    pub options: RenderOptions,
+}
+
+impl Output {
+    /// Output of the given format, written as `<program_name>.<format>` in the current dir,
+    /// with the default options otherwise (see `RenderOptions`).
+    pub fn new(format: Format) -> Self {
+        Self {
+            template: DEFAULT_OUT_TEMPLATE.to_string(),
+            dir: PathBuf::new(),
+            options: RenderOptions::new(format),
+        }
+    }
+
+    /// Output path for the visualization of `idl`.
+    pub fn path(&self, idl: &Idl) -> PathBuf {
+        let file = self
+            .template
+            .replace("{name}", &idl.name)
+            .replace("{version}", &idl.version)
+            .replace("{format}", self.options.format.extension());
+        self.expanded_dir().join(&*shellexpand::tilde(&file))
+    }
+
+    /// Output directory with `~` expanded.
+    fn expanded_dir(&self) -> PathBuf {
+        PathBuf::from(&*shellexpand::tilde(&self.dir.to_string_lossy()))
+    }
+
+    /// Output directory, created as needed.
+    fn prepare_dir(&self) -> Result<PathBuf> {
+        let dir = self.expanded_dir();
+        if !dir.as_os_str().is_empty() {
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// Output path for the visualization of `idl`, creating its parent directories as needed.
+    pub(crate) fn prepare(&self, idl: &Idl) -> Result<PathBuf> {
+        let path = self.path(idl);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+}
+
 /// This function and necessary infrastructure was taken and adapted from anchor-lang & anchor-syn.
 /// It generates an IDL from the source code of an anchor program and loads it into an Idl struct (anchor-syn).
 ///
@@ -21,6 +249,157 @@ fn extract_idl(file: &str, seeds_feature: bool, skip_lint: bool) -> Result<Optio
     anchor_syn::idl::file::parse(&*file, cargo.inner.version(), seeds_feature, !skip_lint)
 }
 
+/// Loads an IDL json file (e.g. `target/idl/my_program.json` from `anchor build`) into an Idl struct.
+/// A path of `-` reads the json from stdin instead.
+pub fn load_idl(path: &str) -> Result<Idl> {
+    let json = if path == "-" {
+        let mut json = String::new();
+        std::io::stdin().read_to_string(&mut json)?;
+        json
+    } else {
+        std::fs::read_to_string(&*shellexpand::tilde(path))?
+    };
+    serde_json::from_str(&json).map_err(|err| {
+        VizError::IdlParse {
+            path: path.to_string(),
+            source: err.into(),
+        }
+        .into()
+    })
+}
+
+/// Extracts the idl of the program at `file`, first with `seeds_feature = seeds` and then,
+/// if that fails, with the seeds feature toggled.
+pub fn extract_idl_with_retry(file: &str, seeds: bool) -> Result<Idl> {
+    let no_program = || VizError::IdlParse {
+        path: file.to_string(),
+        source: anyhow!("no #[program] module found"),
+    };
+    match extract_idl(file, seeds, SKIP_LINT) {
+        Ok(idl) => idl.ok_or_else(no_program).map_err(Into::into),
+        Err(first) => match extract_idl(file, !seeds, SKIP_LINT) {
+            Ok(idl) => idl.ok_or_else(no_program).map_err(Into::into),
+            Err(second) => {
+                let (without_seeds, with_seeds) = if seeds {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+                Err(VizError::SeedsRetryExhausted {
+                    path: file.to_string(),
+                    without_seeds,
+                    with_seeds,
+                }
+                .into())
+            }
+        },
+    }
+}
+
+/// A program to visualize: its idl, plus what its source says beyond the idl.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub idl: Idl,
+    /// Empty when the program was loaded from an idl json
+    pub source: SourceInfo,
+    /// What changed from an older version, for a program merged from two (see `diff::merged`)
+    pub diff: Option<ProgramDiff>,
+    /// Lint findings to mark on the boxes (see `lint::lint`), when linting
+    pub lints: Vec<lint::Finding>,
+}
+
+impl Program {
+    /// Parses the program whose entrypoint is `lib` (its `src/lib.rs`).
+    /// See `extract_idl_with_retry` for `seeds`.
+    pub fn from_source(lib: &Path, seeds: bool) -> Result<Self> {
+        let lib = PathBuf::from(&*shellexpand::tilde(&lib.to_string_lossy()));
+        Ok(Program {
+            idl: extract_idl_with_retry(&lib.to_string_lossy(), seeds)?,
+            // only adds to the idl, so the program is drawn without it if it can't be read
+            source: SourceInfo::parse(&lib).unwrap_or_default(),
+            diff: None,
+            lints: vec![],
+        })
+    }
+
+    /// Loads the program from its idl json (see `load_idl`); there is no source info.
+    pub fn from_idl_json(path: &str) -> Result<Self> {
+        Ok(Program {
+            idl: load_idl(path)?,
+            source: SourceInfo::default(),
+            diff: None,
+            lints: vec![],
+        })
+    }
+
+    /// Loads the program that `spec` names, relative to `dir`: an idl json (a `.json` file, or
+    /// `-` for stdin), a program's `src/lib.rs` or a directory to find the program in (see
+    /// `find_program`), or else a git revision of the program at `dir`.
+    pub fn from_spec(
+        spec: &str,
+        dir: &Path,
+        program_name: Option<&str>,
+        seeds: bool,
+    ) -> Result<Self> {
+        let path = dir.join(&*shellexpand::tilde(spec));
+        if spec == "-" {
+            Program::from_idl_json(spec)
+        } else if spec.ends_with(".json") {
+            Program::from_idl_json(&path.to_string_lossy())
+        } else if path.is_file() {
+            Program::from_source(&path, seeds)
+        } else if path.is_dir() {
+            Program::from_source(&find_program(&path, program_name)?, seeds)
+        } else {
+            Program::from_rev(dir, spec, program_name, seeds)
+        }
+    }
+
+    /// Loads the program as of revision `rev` of the git repository at `dir`: `dir` is checked
+    /// out at that revision into a temporary directory (see `git::Checkout`), where the program
+    /// is found (see `find_program`) and parsed as usual. Source locations are given by their
+    /// path in the repository (see `git::Checkout::repo_path`), as the checkout is removed.
+    pub fn from_rev(
+        dir: &Path,
+        rev: &str,
+        program_name: Option<&str>,
+        seeds: bool,
+    ) -> Result<Self> {
+        let checkout = git::Checkout::new(dir, rev)?;
+        let lib = find_program(checkout.dir(), program_name)?;
+        let mut program = Program::from_source(&lib, seeds)?;
+        program.source.rebase_locations(&|file| checkout.repo_path(file));
+        Ok(program)
+    }
+}
+
+/// Finds the entrypoint (`src/lib.rs`) of a program from `dir`, which is either the program's own
+/// directory or the root of its workspace.
+///
+/// Without a `program_name`, `dir/src/lib.rs` is tried and then the program named after `dir`
+/// in its `programs/` directory. Otherwise the named program in `programs/` is looked for.
+pub fn find_program(dir: &Path, program_name: Option<&str>) -> Result<PathBuf> {
+    let candidates = match program_name {
+        None => {
+            let stem = dir
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            vec![
+                "src/lib.rs".to_string(),
+                format!("programs/{}/src/lib.rs", stem),
+            ]
+        }
+        Some(program_name) => vec![format!("programs/{}/src/lib.rs", program_name)],
+    };
+    candidates
+        .iter()
+        .map(|file| dir.join(&*shellexpand::tilde(file)))
+        .find(|file| file.exists())
+        .ok_or_else(|| VizError::ProgramNotFound { searched: candidates }.into())
+}
+
 /// This struct was taken and adapted from anchor-cli 0.21.0
 #[derive(Debug, Clone, PartialEq)]
 pub struct Manifest(cargo_toml::Manifest);
@@ -74,898 +453,721 @@ impl Manifest {
 }
 
 /// Given a program-name, generate visualization from the idl extracted by anchor-syn.
-/// This function extracts and passses the idl into `visualize(...)` -- the primary backend function.
+/// This function extracts and passses the idl into `render(...)` -- the primary backend function.
 ///
 /// If program-name is None, default to current dir name.
+/// If an idl path is given, the idl is read from that json file (or stdin for `-`)
+/// and program discovery is skipped entirely.
+///
+/// If `output.options.seeds` is set, the program is first parsed with the seeds feature so that
+/// PDA seeds are available, and they are listed on each PDA account box.
+///
+/// The visualization is written to `output.path(..)` (see `Output`).
 ///
 /// This function assumes you are either in the root dir of an anchor program,
 /// e.g. `anchor init my_project` + `cd my_project`,
 /// or in the `my_project/programs/my_program` directory.
+///
+/// Failures are reported as a `VizError` where possible (see `error::exit_code`).
 #[allow(clippy::too_many_arguments, unused_variables)]
 pub fn visual(
     program_name: Option<String>,
-    width: usize,
+    idl_path: Option<String>,
+    output: &Output,
     //viz_args: Vec<String>,
 ) -> Result<()> {
-    
-    // new anchor-cli feature as of 0.22.0
-    const SKIP_LINT: bool = true;
-
     // Grab IDL
     let workspace_dir = std::env::current_dir()?;
 
+    let program = match idl_path.as_ref() {
+        // If an idl json is provided, use it as is
+        Some(idl_path) => Program::from_idl_json(idl_path)?,
+        None => {
+            let lib = find_program(&workspace_dir, program_name.as_deref())?;
+            Program::from_source(&lib, output.options.seeds)?
+        }
+    };
 
-    let idl;
-    let mut extracted_idl;
-    // If no program_name is provided, try extracting at src/lib.rs without seeds_feature
-    if program_name.is_none() {
-        extracted_idl = extract_idl("src/lib.rs", false, SKIP_LINT);
-        if let Ok(my_idl) = extracted_idl {
-            idl = my_idl.unwrap();
-        } else {
-            // then, try with seeds_feature
-            extracted_idl = extract_idl("src/lib.rs", true, SKIP_LINT);
-            if let Ok(my_idl) = extracted_idl {
-                idl = my_idl.unwrap();
-            } else {
-                // then try searching in programs/ directory (with no seeds)
-                let stem = workspace_dir
-                    .file_stem()
-                    .unwrap()
-                    .to_os_string()
-                    .to_str()
-                    .expect("invalid workspace")
-                    .to_string();
-                extracted_idl = extract_idl(&format!("programs/{}/src/lib.rs", stem), false, SKIP_LINT);
-                if let Ok(my_idl) = extracted_idl {
-                    idl = my_idl.unwrap();
-                } else{
-                    // then with seeds
-                    extracted_idl = extract_idl(&format!("programs/{}/src/lib.rs", stem), true, SKIP_LINT);
-                    if let Ok(my_idl) = extracted_idl {
-                        idl = my_idl.unwrap();
-                    } else {
-                        panic!("\n\n\n\nNo program found. Either you are not in an anchor project directory or\nyour ./programs/PROGRAM name must not match your root anchor project directory name.\ncd into your program's directory or try anchorviz -p PROGRAM\n\n\n");
-                    }
-                }
-            }
+    let viz_out = output.prepare(&program.idl)?;
+
+    // Generate visualization
+    render(&program, &viz_out, &output.options)
+}
+
+/// Generates a visualization of the program as of revision `rev` of the git repository of the
+/// current directory (see `Program::from_rev`), like `visual` does for the working tree.
+pub fn visual_rev(
+    rev: &str,
+    program_name: Option<&str>,
+    output: &Output,
+) -> Result<()> {
+    let dir = std::env::current_dir()?;
+    let program = Program::from_rev(&dir, rev, program_name, output.options.seeds)?;
+    let out = output.prepare(&program.idl)?;
+    render(&program, &out, &output.options)
+}
+
+/// Lints the program `spec` names (see `Program::from_spec` and `lint::lint`) and generates its
+/// visualization with the affected boxes marked, written to `output.path(..)` as png, svg or
+/// layout-json. Returns the findings, e.g. to print as diagnostics.
+pub fn visual_lint(
+    spec: &str,
+    program_name: Option<&str>,
+    output: &Output,
+) -> Result<Vec<lint::Finding>> {
+    let dir = std::env::current_dir()?;
+    let mut program = Program::from_spec(spec, &dir, program_name, output.options.seeds)?;
+    program.lints = lint::lint(&program);
+    let out = output.prepare(&program.idl)?;
+    render(&program, &out, &output.options)?;
+    Ok(program.lints)
+}
+
+/// Generates the account usage matrix of the program `spec` names (see `Program::from_spec`)
+/// as png, svg, csv or layout-json (see `render_matrix`), written to `output.path(..)`.
+pub fn visual_matrix(spec: &str, program_name: Option<&str>, output: &Output) -> Result<()> {
+    let dir = std::env::current_dir()?;
+    let program = Program::from_spec(spec, &dir, program_name, output.options.seeds)?;
+    let out = output.prepare(&program.idl)?;
+    render_matrix(&program, &out, &output.options)
+}
+
+/// Generates a visualization of what changed from the program `old` to `new`, each a program
+/// source, idl json or git revision (see `Program::from_spec`): the instructions of both, with
+/// their accounts and args colored as added, removed, changed or unchanged, and what changed
+/// listed under each column.
+///
+/// The visualization is written to `output.path(..)` (see `Output`), as png, svg or layout-json.
+pub fn visual_diff(
+    old: &str,
+    new: &str,
+    program_name: Option<&str>,
+    output: &Output,
+) -> Result<()> {
+    let dir = std::env::current_dir()?;
+    let old = Program::from_spec(old, &dir, program_name, output.options.seeds)?;
+    let new = Program::from_spec(new, &dir, program_name, output.options.seeds)?;
+    let program = diff::merged(&old, &new);
+    let out = output.prepare(&program.idl)?;
+    render(&program, &out, &output.options)
+}
+
+/// Prints which changes from the program `old` to `new` (see `visual_diff`) break clients of
+/// `old`, and fails with `VizError::BreakingChanges` if any do.
+pub fn check_compat(old: &str, new: &str, program_name: Option<&str>, seeds: bool) -> Result<()> {
+    let dir = std::env::current_dir()?;
+    let old = Program::from_spec(old, &dir, program_name, seeds)?;
+    let new = Program::from_spec(new, &dir, program_name, seeds)?;
+    let report = compat::check(&old.idl, &new.idl);
+    print!("{}", report);
+    match report.breaking().count() {
+        0 => Ok(()),
+        count => Err(VizError::BreakingChanges { count }.into()),
+    }
+}
+
+/// Generates a visualization for every program in the anchor workspace at `workspace_dir`,
+/// plus a markdown index (`anchor-viz.md`, in `output.dir`) linking all of them.
+///
+/// Programs are the workspace members listed in Anchor.toml or, failing that, the workspace
+/// Cargo.toml (`programs/*` if neither lists any). A program that fails is reported and listed
+/// in the index with its error, without aborting the rest.
+pub fn visual_all(workspace_dir: &Path, output: &Output) -> Result<()> {
+    // Every program needs its own output
+    if !output.template.contains("{name}") {
+        return Err(VizError::InvalidOutput(output.template.clone()).into());
+    }
+
+    let program_dirs = workspace_programs(workspace_dir)?;
+    if program_dirs.is_empty() {
+        return Err(VizError::ProgramNotFound {
+            searched: vec![workspace_dir.join("programs/*").display().to_string()],
         }
-    } else {
-        // if program_name provided then try that (first without seeds)
-        extracted_idl =  extract_idl(&format!("programs/{}/src/lib.rs", program_name.as_ref().unwrap()), false, SKIP_LINT);
-        if let Ok(my_idl) = extracted_idl{
-            idl = my_idl.unwrap();
-        } else {
-            // with seeds
-            extracted_idl =  extract_idl(&format!("programs/{}/src/lib.rs", program_name.as_ref().unwrap()), true, SKIP_LINT);
-            if let Ok(my_idl) = extracted_idl{
-                idl = my_idl.unwrap();
-            } else {
-                panic!("\n\n\n\nNo program named {}.\ncd into your program's directory or try anchorviz -p PROGRAM again\n\n\n\n", program_name.as_ref().unwrap())
+        .into());
+    }
+
+    let index_dir = output.prepare_dir()?;
+    let mut index = String::from("# Anchor programs\n");
+    let mut failed = vec![];
+    for program_dir in program_dirs.iter() {
+        let lib = program_dir.join("src/lib.rs");
+        let result = Program::from_source(&lib, output.options.seeds).and_then(|program| {
+            let out = output.prepare(&program.idl)?;
+            render(&program, &out, &output.options)?;
+            let entry = index_entry(&program.idl.name, &out, &index_dir, output.options.format)?;
+            Ok((program.idl.name, out, entry))
+        });
+
+        match result {
+            Ok((name, out, entry)) => {
+                println!("ok      {} -> {}", name, out.display());
+                index += &format!("\n## {}\n\n{}\n", name, entry);
+            }
+            Err(err) => {
+                println!("failed  {}: {:#}", program_dir.display(), err);
+                let dir_name = program_dir.file_name().unwrap_or_default().to_string_lossy();
+                index += &format!("\n## {}\n\nFailed: `{:#}`\n", dir_name, err);
+                failed.push(program_dir.display().to_string());
             }
+        }
+    }
+    std::fs::write(index_dir.join("anchor-viz.md"), index)?;
 
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(VizError::ProgramsFailed {
+            failed,
+            total: program_dirs.len(),
         }
+        .into())
     }
+}
 
-    let viz_out: String = workspace_dir
-        .join(format!("{}.png", idl.name))
-        .to_str()
-        .unwrap()
-        .to_string();
+/// How the output `out` of the program `name` appears in the index in `index_dir`: images are
+/// embedded, mermaid charts inlined as a fenced block (which markdown viewers render) and other
+/// formats linked, relative to the index.
+fn index_entry(name: &str, out: &Path, index_dir: &Path, format: Format) -> Result<String> {
+    let link = pathdiff::diff_paths(out, index_dir).unwrap_or_else(|| out.to_path_buf());
+    Ok(match format {
+        Format::Png | Format::Svg => format!("![{}]({})", name, link.display()),
+        Format::Mermaid => format!("```mermaid\n{}```", std::fs::read_to_string(out)?),
+        _ => format!("[{}]({})", name, link.display()),
+    })
+}
 
-    // Generate visualization
-    visualize(idl, &viz_out, width)
-}
-
-/// This function takes in an Idl object (from anchor-syn) and and output path,
-/// and generates a visualization of the instructions of an anchor program.
-fn visualize(idl: Idl, out: &str, width: usize) -> Result<()> {
-    // width and height of fig objects
-    const BOX_PX_WIDTH: usize = 240;
-    const BOX_PX_HEIGHT: usize = 60;
-    // width of header for title
-    const HEADER_PX_HEIGHT: usize = 100;
-    // width of vertical separator
-    const SEP_WIDTH: usize = 2;
-    // vertical and horizontal size of gap between objects
-    const BUFFER_WIDTH: usize = 8;
-    // size of title and other text
-    const TITLE_SIZE: i32 = 24;
-    const TEXT_SIZE: i32 = 20;
-
-    // Find width and height of figure
-    // width: total columns = instructions + state methods
-    let mut state_methods = match idl.state.clone() {
-        Some(idlstate) => idlstate.methods,
-        None => vec![],
-    };
-    let state_name = match idl.state.clone() {
-        Some(idlstate) => idlstate.strct.name,
-        None => "".to_string(),
-    };
-    let columns = idl.instructions.len() + state_methods.len();
-
-    // height: Initialize tracker to find largest instruction/state_method
-    let mut rows = 0;
-    let mut all_instructions = idl.instructions.clone();
-    all_instructions.append(&mut state_methods.clone());
-    for instruction in all_instructions {
-        // count all accounts not in groups
-        let accounts = unpack_group(IdlAccounts {
-            name: "".to_string(),
-            accounts: instruction.accounts.clone(),
-        });
+/// Finds the program directories (those with a `src/lib.rs`) of the anchor workspace at `workspace_dir`.
+/// Members come from the `[workspace]` of Anchor.toml, then of Cargo.toml, then default to `programs/*`;
+/// `exclude`d members of either are dropped. Members of the form `dir/*` are expanded.
+pub fn workspace_programs(workspace_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut members = vec![];
+    let mut exclude = vec![];
 
-        let num_accounts = accounts.len();
-        let acct_height = {
-            if num_accounts % width == 0 {
-                num_accounts / width
-            } else {
-                num_accounts / width + 1
+    let anchor_toml = workspace_dir.join("Anchor.toml");
+    if anchor_toml.exists() {
+        let anchor_toml: toml::Value = std::fs::read_to_string(&anchor_toml)?.parse()?;
+        if let Some(workspace) = anchor_toml.get("workspace") {
+            for (key, list) in [("members", &mut members), ("exclude", &mut exclude)] {
+                if let Some(entries) = workspace.get(key).and_then(|v| v.as_array()) {
+                    list.extend(entries.iter().filter_map(|v| v.as_str()).map(String::from));
+                }
             }
-        };
+        }
+    }
 
-        // count all signers
-        // instruction + group signers
-        let signers = accounts
-            .iter()
-            .fold(0, |acc, x| acc + if x.is_signer { 1 } else { 0 });
-
-        let sign_height = {
-            if signers % width == 0 {
-                signers / width
-            } else {
-                signers / width + 1
+    let cargo_toml = workspace_dir.join("Cargo.toml");
+    if cargo_toml.exists() {
+        if let Some(workspace) = Manifest::from_path(&cargo_toml)?.0.workspace {
+            if members.is_empty() {
+                members = workspace.members;
             }
+            exclude.extend(workspace.exclude);
         }
-        .max(1);
-
-        let args = instruction.args.len();
-        let arg_height = {
-            if args % width == 0 {
-                args / width
-            } else {
-                args / width + 1
+    }
+
+    if members.is_empty() {
+        members.push("programs/*".to_string());
+    }
+
+    let mut program_dirs = vec![];
+    for member in members {
+        match member.strip_suffix("/*") {
+            Some(parent) => {
+                let parent = workspace_dir.join(parent);
+                if !parent.is_dir() {
+                    continue;
+                }
+                let mut children = std::fs::read_dir(parent)?
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<std::io::Result<Vec<_>>>()?;
+                children.sort();
+                program_dirs.extend(children);
             }
-        };
+            None => program_dirs.push(workspace_dir.join(member)),
+        }
+    }
 
-        let height = arg_height + acct_height + sign_height;
-        rows = rows.max(height);
-    }
-
-    // Steps to take
-    // 0) Create a canvas to draw on
-    // 1) Title and version
-    // 2) Populate vertical separator lines
-    // 3) Populate anchor instruction names
-    // 4) Populate mut accts
-    // 5) Populate immut accounts
-    // 6) Populate signers
-    // 7) populate args
-
-    // 0) Create a canvas to draw on
-    let fig_width: u32 = ((BOX_PX_WIDTH + BUFFER_WIDTH) * width * columns
-        + BUFFER_WIDTH * columns
-        + (columns - 1) * SEP_WIDTH)
-        .try_into()
-        .unwrap();
-    let fig_height: u32 = ((BOX_PX_HEIGHT + BUFFER_WIDTH) * rows
-        + HEADER_PX_HEIGHT
-        + 3 * BUFFER_WIDTH
-        + BOX_PX_HEIGHT
-        + 2 * BUFFER_WIDTH)
-        .try_into()
-        .unwrap();
-    let backend = BitMapBackend::new(out, (fig_width, fig_height)).into_drawing_area();
-    backend
-        .fill(&WHITE)
-        .expect("couldn't fill background color");
+    let exclude: Vec<PathBuf> = exclude.iter().map(|dir| workspace_dir.join(dir)).collect();
+    program_dirs.retain(|dir| !exclude.contains(dir) && dir.join("src/lib.rs").exists());
+    program_dirs.dedup();
+    Ok(program_dirs)
+}
 
-    // 1) Title and version
-    backend
-        .draw(&Text::new(
-            format!("Anchor Program: {}", idl.name),
-            (fig_width as i32 / 2, HEADER_PX_HEIGHT as i32 / 4),
-            TextStyle {
-                font: FontDesc::new(FontFamily::Monospace, TITLE_SIZE as f64, FontStyle::Bold),
-                color: BackendColor {
-                    alpha: 1.0,
-                    rgb: (0, 0, 0),
-                },
-                pos: Pos {
-                    h_pos: HPos::Center,
-                    v_pos: VPos::Center,
-                },
-            },
-        ))
-        .expect("couldn't write 'Anchor Program'");
-    backend
-        .draw(&Text::new(
-            format!("Version: {}", idl.version),
-            (fig_width as i32 / 2, HEADER_PX_HEIGHT as i32 / 2),
-            TextStyle {
-                font: FontDesc::new(FontFamily::Monospace, TITLE_SIZE as f64, FontStyle::Normal),
-                color: BackendColor {
-                    alpha: 1.0,
-                    rgb: (0, 0, 0),
-                },
-                pos: Pos {
-                    h_pos: HPos::Center,
-                    v_pos: VPos::Center,
-                },
-            },
-        ))
-        .expect("couldn't write version");
+/// This function takes in a program (its idl, from anchor-syn, and source info) and an output path,
+/// and writes a visualization of the instructions of the anchor program as `options` say.
+///
+/// `options.width` is the number of accounts, arguments per instruction column; PDA seeds are
+/// listed if `options.seeds` is set. Account types and constraints read from the program source
+/// are shown on the account boxes. `options.fit` says how text too long for its box is handled,
+/// and `options.theme` gives the colors, font and sizes.
+///
+/// Doc comments from the source show as tooltips in svg and html. If `options.footnotes` is set,
+/// png and svg also list them under each column as footnotes, numbered on the boxes.
+///
+/// A program merged from two versions (see `diff::merged`) is drawn as their diff, and the lint
+/// findings of a program (see `lint::lint`) as warning badges on its boxes, which only png, svg
+/// and layout-json can show. Csv is only for the account matrix (see `render_matrix`).
+pub fn render(program: &Program, out: &Path, options: &RenderOptions) -> Result<()> {
+    let (format, width, theme) = (options.format, options.width.get(), &options.theme);
+    let marks_boxes = matches!(format, Format::Png | Format::Svg | Format::LayoutJson);
+    if program.diff.is_some() && !marks_boxes {
+        return Err(unsupported(format, "a diff", "png, svg or layout-json"));
+    }
+    if !program.lints.is_empty() && !marks_boxes {
+        return Err(unsupported(format, "lint warnings", "png, svg or layout-json"));
+    }
 
-    // 2) Vertical Separator lines
-    for i in 1..columns {
-        // Thin Rectangles as vertical separators
-        backend
-            .draw(&Rectangle::new(
-                [
-                    (
-                        // top left
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            - SEP_WIDTH) as i32,
-                        (HEADER_PX_HEIGHT + BUFFER_WIDTH) as i32,
-                    ),
-                    (
-                        // bottom right
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i) as i32,
-                        // ((rows+1)*BOX_PX_HEIGHT + HEADER_PX_HEIGHT + (rows + 2)*BUFFER_WIDTH) as i32, // idk why this doesn't work... this is (in principle) fig_height - 3*BUFFER_WIDTH
-                        (fig_height as i32 - 3 * BUFFER_WIDTH as i32),
-                    ),
-                ],
-                Into::<ShapeStyle>::into(&BLACK).filled(),
-            ))
-            .expect("couldn't draw vertical separators");
+    // Text based formats are laid out by whatever renders them
+    match format {
+        Format::Html => return html::explorer(program, out, width, options.fit, theme),
+        Format::Mermaid => return mermaid::write(&program.idl, out, theme),
+        Format::Dot => return dot::write(&program.idl, out, theme),
+        Format::Csv => {
+            let supported = "png, svg, html, mermaid, dot or layout-json (csv is for a matrix)";
+            return Err(unsupported(format, "a visualization", supported));
+        }
+        Format::Png | Format::Svg | Format::LayoutJson => {}
     }
 
-    // 3) Populate instruction + state method names
-    // 4) Populate signers
-    // 5) Populate mut accts
-    // 6) Populate immut accounts
-    // 7) populate args
+    let layout = layout::layout(
+        program,
+        width,
+        options.seeds,
+        options.footnotes,
+        options.fit,
+        theme,
+    );
+    let dimensions: (u32, u32) = (layout.width.try_into()?, layout.height.try_into()?);
 
-    // 3) Populate instruction + state method names
-    for (i, instruction) in idl.instructions.iter().enumerate() {
+    // Same layout, different canvas
+    match format {
+        Format::Png => draw(BitMapBackend::new(out, dimensions).into_drawing_area(), &layout),
+        Format::Svg => draw(SVGBackend::new(out, dimensions).into_drawing_area(), &layout)
+            .and_then(|_| add_svg_tooltips(out, &layout)),
+        Format::LayoutJson => std::fs::write(out, serde_json::to_string_pretty(&layout)?)
+            .map_err(|err| VizError::backend_write("couldn't write layout", err).into()),
+        Format::Html | Format::Mermaid | Format::Dot | Format::Csv => {
+            unreachable!("handled above")
+        }
+    }
+}
+
+/// Renders the account usage matrix of `program` (see `matrix::account_matrix`) to `out`: drawn
+/// as a grid in png or svg, as its `matrix::MatrixLayout` in layout-json, or as csv, in the
+/// format and theme of `options`.
+pub fn render_matrix(program: &Program, out: &Path, options: &RenderOptions) -> Result<()> {
+    let (format, theme) = (options.format, &options.theme);
+    let account_matrix = matrix::account_matrix(&program.idl);
+    let layout = match format {
+        Format::Csv => return matrix::write_csv(&program.idl, out),
+        Format::Png | Format::Svg | Format::LayoutJson => matrix::layout(&account_matrix, theme),
+        Format::Html | Format::Mermaid | Format::Dot => {
+            return Err(unsupported(format, "an account matrix", "png, svg, csv or layout-json"))
+        }
+    };
+    let dimensions: (u32, u32) = (layout.width.try_into()?, layout.height.try_into()?);
+    match format {
+        Format::Png => {
+            draw_matrix(BitMapBackend::new(out, dimensions).into_drawing_area(), &layout)
+        }
+        Format::Svg => draw_matrix(SVGBackend::new(out, dimensions).into_drawing_area(), &layout),
+        _ => std::fs::write(out, serde_json::to_string_pretty(&layout)?)
+            .map_err(|err| VizError::backend_write("couldn't write layout", err).into()),
+    }
+}
+
+fn unsupported(format: Format, what: &str, supported: &str) -> anyhow::Error {
+    VizError::UnsupportedFormat {
+        // as passed to --format, e.g. layout-json
+        format: format
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_else(|| format.extension().to_string()),
+        what: what.to_string(),
+        supported: supported.to_string(),
+    }
+    .into()
+}
+
+/// Draws an account matrix: its title and legend, a cell in the color of each access, and the
+/// grid over them.
+fn draw_matrix<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    layout: &MatrixLayout,
+) -> Result<()> {
+    let theme = &layout.theme;
+    let (foreground, background) = (theme.colors.foreground, theme.colors.background);
+    backend
+        .fill(&rgb(background))
+        .map_err(|err| VizError::backend_write("couldn't fill background color", err))?;
+    draw_line(&backend, theme, &layout.title, FontStyle::Bold, foreground)
+        .and_then(|_| draw_line(&backend, theme, &layout.legend, FontStyle::Normal, foreground))
+        .map_err(|err| VizError::backend_write("couldn't write matrix title", err))?;
+    for line in layout.accounts.iter().chain(&layout.instructions) {
+        draw_line(&backend, theme, line, FontStyle::Normal, foreground)
+            .map_err(|err| VizError::backend_write("couldn't write matrix names", err))?;
+    }
+    for cell in &layout.cells {
         backend
             .draw(&Rectangle::new(
-                [
-                    (
-                        // top left
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2
-                            - BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT + BUFFER_WIDTH) as i32,
-                    ),
-                    (
-                        // bottom right
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2
-                            + BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT + BUFFER_WIDTH + BOX_PX_HEIGHT) as i32,
-                    ),
-                ],
-                Into::<ShapeStyle>::into(&RGBColor(255, 200, 200)).filled(),
+                corners(&cell.rect),
+                Into::<ShapeStyle>::into(&rgb(theme.color(cell.role))).filled(),
             ))
-            .expect("couldn't draw rect for instruction");
-        backend
-            .draw(&Text::new(
-                "Instruction:".to_string(),
-                (
-                    ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH) * i
-                        + BUFFER_WIDTH
-                        + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2)
-                        as i32,
-                    (HEADER_PX_HEIGHT + BUFFER_WIDTH + BOX_PX_HEIGHT / 3) as i32,
-                ),
-                TextStyle {
-                    font: FontDesc::new(FontFamily::Monospace, TEXT_SIZE as f64, FontStyle::Normal),
-                    color: BackendColor {
-                        alpha: 1.0,
-                        rgb: (0, 0, 0),
-                    },
-                    pos: Pos {
-                        h_pos: HPos::Center,
-                        v_pos: VPos::Center,
-                    },
-                },
-            ))
-            .expect("couldn't write 'Instruction'");
-        backend
-            .draw(&Text::new(
-                instruction.name.to_string(),
-                (
-                    ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH) * i
-                        + BUFFER_WIDTH
-                        + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2)
-                        as i32,
-                    (HEADER_PX_HEIGHT + BUFFER_WIDTH + 2 * BOX_PX_HEIGHT / 3) as i32,
-                ),
-                TextStyle {
-                    font: FontDesc::new(FontFamily::Monospace, TEXT_SIZE as f64, FontStyle::Normal),
-                    color: BackendColor {
-                        alpha: 1.0,
-                        rgb: (0, 0, 0),
-                    },
-                    pos: Pos {
-                        h_pos: HPos::Center,
-                        v_pos: VPos::Center,
-                    },
-                },
-            ))
-            .expect("couldn't write instruction name");
+            .and_then(|_| {
+                draw_line(&backend, theme, &cell.code, FontStyle::Bold, theme.colors.text)
+            })
+            .map_err(|err| VizError::backend_write("couldn't draw matrix cell", err))?;
     }
-    for (i, state_method) in state_methods.iter().enumerate() {
-        let i = i + idl.instructions.len();
+    for line in &layout.grid {
         backend
             .draw(&Rectangle::new(
-                [
-                    (
-                        // top left
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2
-                            - BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT + BUFFER_WIDTH) as i32,
-                    ),
-                    (
-                        // bottom right
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2
-                            + BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT + BUFFER_WIDTH + BOX_PX_HEIGHT) as i32,
-                    ),
-                ],
-                Into::<ShapeStyle>::into(&RGBColor(255, 200, 200)).filled(),
+                corners(line),
+                Into::<ShapeStyle>::into(&rgb(foreground)).filled(),
             ))
-            .expect("couldn't draw rect for instruction");
+            .map_err(|err| VizError::backend_write("couldn't draw matrix grid", err))?;
+    }
+    Ok(())
+}
+
+/// Draws `layout` onto a drawing area of its size, in the colors and font of its theme.
+/// This is backend-agnostic so that the png and svg outputs share one layout.
+fn draw<DB: DrawingBackend>(backend: DrawingArea<DB, Shift>, layout: &Layout) -> Result<()> {
+    let theme = &layout.theme;
+    let (foreground, background) = (theme.colors.foreground, theme.colors.background);
+    backend
+        .fill(&rgb(background))
+        .map_err(|err| VizError::backend_write("couldn't fill background color", err))?;
+
+    // 1) Title, version and role (or, in a diff, status) legend
+    draw_line(&backend, theme, &layout.title, FontStyle::Bold, foreground)
+        .map_err(|err| VizError::backend_write("couldn't write 'Anchor Program'", err))?;
+    draw_line(&backend, theme, &layout.version, FontStyle::Normal, foreground)
+        .map_err(|err| VizError::backend_write("couldn't write version", err))?;
+    let roles = layout
+        .roles
+        .iter()
+        .map(|entry| (&entry.swatch, theme.color(entry.role), &entry.description));
+    let statuses = layout
+        .statuses
+        .iter()
+        .map(|entry| (&entry.swatch, theme.status_color(entry.status), &entry.description));
+    for (swatch, color, description) in roles.chain(statuses) {
         backend
-            .draw(&Text::new(
-                "State Method:".to_string(),
-                (
-                    ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH) * i
-                        + BUFFER_WIDTH
-                        + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2)
-                        as i32,
-                    (HEADER_PX_HEIGHT + BUFFER_WIDTH + BOX_PX_HEIGHT / 3) as i32,
-                ),
-                TextStyle {
-                    font: FontDesc::new(FontFamily::Monospace, TEXT_SIZE as f64, FontStyle::Normal),
-                    color: BackendColor {
-                        alpha: 1.0,
-                        rgb: (0, 0, 0),
-                    },
-                    pos: Pos {
-                        h_pos: HPos::Center,
-                        v_pos: VPos::Center,
-                    },
-                },
+            .draw(&Rectangle::new(
+                corners(swatch),
+                Into::<ShapeStyle>::into(&rgb(color)).filled(),
             ))
-            .expect("couldn't write 'State Method'");
+            .and_then(|_| {
+                backend.draw(&Rectangle::new(
+                    corners(swatch),
+                    Into::<ShapeStyle>::into(&rgb(foreground)),
+                ))
+            })
+            .and_then(|_| draw_line(&backend, theme, description, FontStyle::Normal, foreground))
+            .map_err(|err| VizError::backend_write("couldn't draw color legend", err))?;
+    }
+
+    // 2) Vertical Separator lines
+    for separator in &layout.separators {
         backend
-            .draw(&Text::new(
-                format!("{}.{}", state_name, state_method.name),
-                (
-                    ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH) * i
-                        + BUFFER_WIDTH
-                        + (BOX_PX_WIDTH * width + BUFFER_WIDTH * (width + 1)) / 2)
-                        as i32,
-                    (HEADER_PX_HEIGHT + BUFFER_WIDTH + 2 * BOX_PX_HEIGHT / 3) as i32,
-                ),
-                TextStyle {
-                    font: FontDesc::new(FontFamily::Monospace, TEXT_SIZE as f64, FontStyle::Normal),
-                    color: BackendColor {
-                        alpha: 1.0,
-                        rgb: (0, 0, 0),
-                    },
-                    pos: Pos {
-                        h_pos: HPos::Center,
-                        v_pos: VPos::Center,
-                    },
-                },
+            .draw(&Rectangle::new(
+                corners(separator),
+                Into::<ShapeStyle>::into(&rgb(foreground)).filled(),
             ))
-            .expect("couldn't write state method name");
+            .map_err(|err| VizError::backend_write("couldn't draw vertical separators", err))?;
     }
 
-    // concat all instructions + methods
-    let mut all_instructions = idl.instructions;
-    all_instructions.append(&mut state_methods);
-    for (i, instruction) in all_instructions.iter().enumerate() {
-        let accounts = unpack_group(IdlAccounts {
-            name: "".to_string(),
-            accounts: instruction.accounts.clone(),
-        });
+    // 3) Instruction + state method names, 4) signers, 5) accounts, 6) account groups, 7) args
+    for column in &layout.columns {
+        draw_box(&backend, theme, &column.instruction)?;
+        for signer in &column.signers {
+            draw_box(&backend, theme, signer)?;
+        }
+        for account in &column.accounts {
+            draw_box(&backend, theme, account)?;
+        }
+        for group in &column.groups {
+            draw_group(&backend, theme, group)?;
+        }
+        for arg in &column.args {
+            draw_box(&backend, theme, arg)?;
+        }
+        for footnote in &column.footnotes {
+            draw_line(&backend, theme, footnote, FontStyle::Normal, foreground)
+                .map_err(|err| VizError::backend_write("couldn't write footnotes", err))?;
+        }
+    }
 
-        let inst_signers: Vec<&IdlAccount> = accounts.iter().filter(|&x| x.is_signer).collect();
-        let mut signers = 0; // counter
-                             // 4) Populate signers
-        for &signer in inst_signers.iter() {
-            let (l, k) = (signers / width, signers % width);
-
-            backend
-                .draw(&Rectangle::new(
-                    [
-                        (
-                            // top left
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * k) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BOX_PX_HEIGHT
-                                + BUFFER_WIDTH * (1 + l)
-                                + BOX_PX_HEIGHT * (l)) as i32,
-                        ),
-                        (
-                            // bottom right
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * (k + 1)) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BOX_PX_HEIGHT
-                                + BUFFER_WIDTH * (1 + l)
-                                + BOX_PX_HEIGHT * (l + 1)) as i32,
-                        ),
-                    ],
-                    Into::<ShapeStyle>::into(&RGBColor(0, 255, 163)).filled(),
-                ))
-                .expect("couldn't draw rect for signer");
-            backend
-                .draw(&Text::new(
-                    "Signer:".to_string(),
-                    (
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + BUFFER_WIDTH * (k + 1)
-                            + BOX_PX_WIDTH * k
-                            + BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT
-                            + 2 * BUFFER_WIDTH
-                            + BUFFER_WIDTH * (1 + l)
-                            + BOX_PX_HEIGHT * (l + 1)
-                            + BOX_PX_HEIGHT / 3) as i32,
-                    ),
-                    TextStyle {
-                        font: FontDesc::new(
-                            FontFamily::Monospace,
-                            TEXT_SIZE as f64,
-                            FontStyle::Normal,
-                        ),
-                        color: BackendColor {
-                            alpha: 1.0,
-                            rgb: (0, 0, 0),
-                        },
-                        pos: Pos {
-                            h_pos: HPos::Center,
-                            v_pos: VPos::Center,
-                        },
-                    },
-                ))
-                .expect("couldn't write 'Signer:'");
-            backend
-                .draw(&Text::new(
-                    signer.name.to_string(),
-                    (
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + BUFFER_WIDTH * (k + 1)
-                            + BOX_PX_WIDTH * k
-                            + BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT
-                            + 2 * BUFFER_WIDTH
-                            + BUFFER_WIDTH * (1 + l)
-                            + BOX_PX_HEIGHT * (l + 1)
-                            + 2 * BOX_PX_HEIGHT / 3) as i32,
-                    ),
-                    TextStyle {
-                        font: FontDesc::new(
-                            FontFamily::Monospace,
-                            TEXT_SIZE as f64,
-                            FontStyle::Normal,
-                        ),
-                        color: BackendColor {
-                            alpha: 1.0,
-                            rgb: (0, 0, 0),
-                        },
-                        pos: Pos {
-                            h_pos: HPos::Center,
-                            v_pos: VPos::Center,
-                        },
-                    },
-                ))
-                .expect("couldn't write signer");
+    // 8) Account structs and custom types
+    if let Some(types) = &layout.types {
+        backend
+            .draw(&Rectangle::new(
+                corners(&types.separator),
+                Into::<ShapeStyle>::into(&rgb(foreground)).filled(),
+            ))
+            .map_err(|err| VizError::backend_write("couldn't draw types separator", err))?;
+        draw_line(&backend, theme, &types.label, FontStyle::Bold, foreground)
+            .map_err(|err| VizError::backend_write("couldn't write 'Types'", err))?;
+        for card in &types.cards {
+            draw_type_card(&backend, theme, card)?;
+        }
+    }
 
-            signers += 1;
+    if let Some(legend) = &layout.legend {
+        draw_line(&backend, theme, &legend.label, FontStyle::Bold, foreground)
+            .map_err(|err| VizError::backend_write("couldn't write legend", err))?;
+        for entry in &legend.entries {
+            draw_badge(&backend, theme, &entry.badge, foreground, background)?;
+            draw_line(&backend, theme, &entry.description, FontStyle::Normal, foreground)
+                .map_err(|err| VizError::backend_write("couldn't write legend", err))?;
         }
+    }
 
-        let signer_offset = {
-            if signers % width == 0 {
-                signers / width
-            } else {
-                signers / width + 1
-            }
-            .max(1)
-        };
+    // Flush to the output file
+    backend
+        .present()
+        .map_err(|err| VizError::backend_write("couldn't write output", err))?;
+    Ok(())
+}
 
-        let mut accounts_drawn = 0;
-
-        // 5) Populate mut accts
-        for account in accounts.clone() {
-            if account.is_mut {
-                let (l, k) = (accounts_drawn / width, accounts_drawn % width);
-
-                backend
-                    .draw(&Rectangle::new(
-                        [
-                            (
-                                // top left
-                                ((BOX_PX_WIDTH * width
-                                    + SEP_WIDTH
-                                    + (1 + width) * BUFFER_WIDTH)
-                                    * i
-                                    + BUFFER_WIDTH * (k + 1)
-                                    + BOX_PX_WIDTH * k) as i32,
-                                (HEADER_PX_HEIGHT
-                                    + 2 * BUFFER_WIDTH
-                                    + BOX_PX_HEIGHT
-                                    + BUFFER_WIDTH * (1 + signer_offset + l)
-                                    + BOX_PX_HEIGHT * (l + signer_offset))
-                                    as i32,
-                            ),
-                            (
-                                // bottom right
-                                ((BOX_PX_WIDTH * width
-                                    + SEP_WIDTH
-                                    + (1 + width) * BUFFER_WIDTH)
-                                    * i
-                                    + BUFFER_WIDTH * (k + 1)
-                                    + BOX_PX_WIDTH * (k + 1))
-                                    as i32,
-                                (HEADER_PX_HEIGHT
-                                    + 2 * BUFFER_WIDTH
-                                    + BOX_PX_HEIGHT
-                                    + BUFFER_WIDTH * (1 + signer_offset + l)
-                                    + BOX_PX_HEIGHT * (l + 1 + signer_offset))
-                                    as i32,
-                            ),
-                        ],
-                        Into::<ShapeStyle>::into(&RGBColor(255, 100, 100)).filled(),
-                    ))
-                    .expect("couldn't draw rect for mutable account");
-                backend
-                    .draw(&Text::new(
-                        "Mutable Account:".to_string(),
-                        (
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * k
-                                + BOX_PX_WIDTH / 2) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BUFFER_WIDTH * (signer_offset + l)
-                                + BOX_PX_HEIGHT * (l + 1 + signer_offset)
-                                + BOX_PX_HEIGHT / 3) as i32,
-                        ),
-                        TextStyle {
-                            font: FontDesc::new(
-                                FontFamily::Monospace,
-                                TEXT_SIZE as f64,
-                                FontStyle::Normal,
-                            ),
-                            color: BackendColor {
-                                alpha: 1.0,
-                                rgb: (0, 0, 0),
-                            },
-                            pos: Pos {
-                                h_pos: HPos::Center,
-                                v_pos: VPos::Center,
-                            },
-                        },
-                    ))
-                    .expect("couldn't write 'Mutable Account:'");
-                backend
-                    .draw(&Text::new(
-                        account.name.to_string(),
-                        (
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * k
-                                + BOX_PX_WIDTH / 2) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BUFFER_WIDTH * (1 + signer_offset + l)
-                                + BOX_PX_HEIGHT * (l + 1 + signer_offset)
-                                + 2 * BOX_PX_HEIGHT / 3) as i32,
-                        ),
-                        TextStyle {
-                            font: FontDesc::new(
-                                FontFamily::Monospace,
-                                TEXT_SIZE as f64,
-                                FontStyle::Normal,
-                            ),
-                            color: BackendColor {
-                                alpha: 1.0,
-                                rgb: (0, 0, 0),
-                            },
-                            pos: Pos {
-                                h_pos: HPos::Center,
-                                v_pos: VPos::Center,
-                            },
-                        },
-                    ))
-                    .expect("couldn't write mut account name");
-
-                accounts_drawn += 1;
+/// Adds the doc comments of the boxes in `layout` to the svg at `out` as tooltips: an invisible
+/// rect with a `<title>` over each documented box, which plotters has no way to draw.
+fn add_svg_tooltips(out: &Path, layout: &Layout) -> Result<()> {
+    let tooltips: String = layout
+        .columns
+        .iter()
+        .flat_map(|column| column.boxes())
+        .filter_map(|b| {
+            let lints = b.lints.iter().map(|lint| format!("warning: {}", lint));
+            let text: Vec<String> = b.doc.iter().cloned().chain(lints).collect();
+            if text.is_empty() {
+                return None;
             }
-        }
+            Some(format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill-opacity=\"0\">\
+                 <title>{}</title></rect>\n",
+                b.rect.left,
+                b.rect.top,
+                b.rect.width,
+                b.rect.height,
+                xml_escape(&text.join("\n"))
+            ))
+        })
+        .collect();
+    if tooltips.is_empty() {
+        return Ok(());
+    }
+    let write_error = |err| VizError::backend_write("couldn't add tooltips", err);
+    let svg = std::fs::read_to_string(out).map_err(write_error)?;
+    let svg = match svg.rfind("</svg>") {
+        Some(end) => format!("{}{}{}", &svg[..end], tooltips, &svg[end..]),
+        None => svg,
+    };
+    std::fs::write(out, svg).map_err(|err| write_error(err).into())
+}
 
-        // 6) Populate immut accts
-        for account in &accounts.clone() {
-            if !account.is_mut {
-                let (l, k) = (accounts_drawn / width, accounts_drawn % width);
-
-                backend
-                    .draw(&Rectangle::new(
-                        [
-                            (
-                                // top left
-                                ((BOX_PX_WIDTH * width
-                                    + SEP_WIDTH
-                                    + (1 + width) * BUFFER_WIDTH)
-                                    * i
-                                    + BUFFER_WIDTH * (k + 1)
-                                    + BOX_PX_WIDTH * k) as i32,
-                                (HEADER_PX_HEIGHT
-                                    + 2 * BUFFER_WIDTH
-                                    + BOX_PX_HEIGHT
-                                    + BUFFER_WIDTH * (1 + signer_offset + l)
-                                    + BOX_PX_HEIGHT * (l + signer_offset))
-                                    as i32,
-                            ),
-                            (
-                                // bottom right
-                                ((BOX_PX_WIDTH * width
-                                    + SEP_WIDTH
-                                    + (1 + width) * BUFFER_WIDTH)
-                                    * i
-                                    + BUFFER_WIDTH * (k + 1)
-                                    + BOX_PX_WIDTH * (k + 1))
-                                    as i32,
-                                (HEADER_PX_HEIGHT
-                                    + 2 * BUFFER_WIDTH
-                                    + BOX_PX_HEIGHT
-                                    + BUFFER_WIDTH * (1 + signer_offset + l)
-                                    + BOX_PX_HEIGHT * (l + signer_offset + 1))
-                                    as i32,
-                            ),
-                        ],
-                        Into::<ShapeStyle>::into(&RGBColor(3, 225, 255)).filled(),
-                    ))
-                    .expect("couldn't draw rect for immutable account");
-                backend
-                    .draw(&Text::new(
-                        "Immutable Account:".to_string(),
-                        (
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * k
-                                + BOX_PX_WIDTH / 2) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BUFFER_WIDTH * (1 + signer_offset + l)
-                                + BOX_PX_HEIGHT * (l + 1 + signer_offset)
-                                + BOX_PX_HEIGHT / 3) as i32,
-                        ),
-                        TextStyle {
-                            font: FontDesc::new(
-                                FontFamily::Monospace,
-                                TEXT_SIZE as f64,
-                                FontStyle::Normal,
-                            ),
-                            color: BackendColor {
-                                alpha: 1.0,
-                                rgb: (0, 0, 0),
-                            },
-                            pos: Pos {
-                                h_pos: HPos::Center,
-                                v_pos: VPos::Center,
-                            },
-                        },
-                    ))
-                    .expect("couldn't write 'Immutable Account:'");
-                backend
-                    .draw(&Text::new(
-                        account.name.to_string(),
-                        (
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * k
-                                + BOX_PX_WIDTH / 2) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BUFFER_WIDTH * (1 + signer_offset + l)
-                                + BOX_PX_HEIGHT * (l + 1 + signer_offset)
-                                + 2 * BOX_PX_HEIGHT / 3) as i32,
-                        ),
-                        TextStyle {
-                            font: FontDesc::new(
-                                FontFamily::Monospace,
-                                TEXT_SIZE as f64,
-                                FontStyle::Normal,
-                            ),
-                            color: BackendColor {
-                                alpha: 1.0,
-                                rgb: (0, 0, 0),
-                            },
-                            pos: Pos {
-                                h_pos: HPos::Center,
-                                v_pos: VPos::Center,
-                            },
-                        },
-                    ))
-                    .expect("couldn't write immut account name");
-
-                accounts_drawn += 1;
-            }
-        }
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
 
-        let account_offset = {
-            if accounts_drawn % width == 0 {
-                accounts_drawn / width
-            } else {
-                accounts_drawn / width + 1
-            }
+/// Top left and bottom right corners of `rect`, as plotters wants them.
+fn corners(rect: &Rect) -> [(i32, i32); 2] {
+    [
+        (rect.left as i32, rect.top as i32),
+        (rect.right() as i32, rect.bottom() as i32),
+    ]
+}
+
+fn rgb(color: Color) -> RGBColor {
+    RGBColor(color.0, color.1, color.2)
+}
+
+/// Draws a line of text in the theme's font, with the given font size, style and color.
+fn draw_line<DB: DrawingBackend>(
+    backend: &DrawingArea<DB, Shift>,
+    theme: &Theme,
+    line: &Line,
+    style: FontStyle,
+    color: Color,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    backend.draw(&Text::new(
+        line.text.clone(),
+        (line.x as i32, line.y as i32),
+        TextStyle {
+            font: FontDesc::new(
+                FontFamily::from(theme.font.family.as_str()),
+                line.size as f64,
+                style,
+            ),
+            color: BackendColor {
+                alpha: 1.0,
+                rgb: (color.0, color.1, color.2),
+            },
+            pos: Pos {
+                h_pos: match line.align {
+                    Align::Left => HPos::Left,
+                    Align::Center => HPos::Center,
+                },
+                v_pos: VPos::Center,
+            },
+        },
+    ))
+}
+
+/// Draws a box filled with its role's color, with its label and name (and, for accounts,
+/// their type, constraint badges, unchecked flag and PDA badge and seeds).
+fn draw_box<DB: DrawingBackend>(
+    backend: &DrawingArea<DB, Shift>,
+    theme: &Theme,
+    b: &LayoutBox,
+) -> Result<()> {
+    let what = b.role.name();
+    let text = theme.colors.text;
+    // in a diff, boxes are colored by how they changed instead
+    let fill = match &b.diff {
+        Some(diff) => theme.status_color(diff.status),
+        None => theme.color(b.role),
+    };
+    backend
+        .draw(&Rectangle::new(
+            corners(&b.rect),
+            Into::<ShapeStyle>::into(&rgb(fill)).filled(),
+        ))
+        .map_err(|err| VizError::backend_write(&format!("couldn't draw rect for {}", what), err))?;
+    for line in b.label_lines.iter().chain(&b.name_lines) {
+        draw_line(backend, theme, line, FontStyle::Normal, text)
+            .map_err(|err| VizError::backend_write(&format!("couldn't write {}", what), err))?;
+    }
+
+    if let Some(ty) = &b.ty {
+        draw_line(backend, theme, ty, FontStyle::Italic, text)
+            .map_err(|err| VizError::backend_write("couldn't write account type", err))?;
+    }
+    for badge in &b.badges {
+        draw_badge(backend, theme, badge, theme.colors.foreground, theme.colors.background)?;
+    }
+    if b.unchecked {
+        draw_unchecked(backend, theme, &b.rect)?;
+    }
+    if let Some(warning) = &b.warning {
+        draw_badge(backend, theme, warning, theme.colors.unchecked, theme.colors.text)?;
+    }
+    if let Some(pda) = &b.pda {
+        let badge = Badge {
+            text: "PDA".to_string(),
+            rect: pda.badge,
         };
-        let offset = signer_offset + account_offset;
-
-        // 7) Populate args
-        for (args_drawn, arg) in instruction.args.iter().enumerate() {
-            let (l, k) = (args_drawn / width, args_drawn % width);
-
-            backend
-                .draw(&Rectangle::new(
-                    [
-                        (
-                            // top left
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * k) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BOX_PX_HEIGHT
-                                + BUFFER_WIDTH * (1 + offset + l)
-                                + BOX_PX_HEIGHT * (l + offset)) as i32,
-                        ),
-                        (
-                            // bottom right
-                            ((BOX_PX_WIDTH * width
-                                + SEP_WIDTH
-                                + (1 + width) * BUFFER_WIDTH)
-                                * i
-                                + BUFFER_WIDTH * (k + 1)
-                                + BOX_PX_WIDTH * (k + 1)) as i32,
-                            (HEADER_PX_HEIGHT
-                                + 2 * BUFFER_WIDTH
-                                + BOX_PX_HEIGHT
-                                + BUFFER_WIDTH * (1 + offset + l)
-                                + BOX_PX_HEIGHT * (l + offset + 1))
-                                as i32,
-                        ),
-                    ],
-                    Into::<ShapeStyle>::into(&RGBColor(220, 31, 255)).filled(),
-                ))
-                .expect("couldn't draw rect for argument");
-            backend
-                .draw(&Text::new(
-                    //"Argument:".to_string(),
-                    format!("{:?}:", arg.ty).to_lowercase(),
-                    (
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + BUFFER_WIDTH * (k + 1)
-                            + BOX_PX_WIDTH * k
-                            + BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT
-                            + 2 * BUFFER_WIDTH
-                            + BUFFER_WIDTH * (1 + offset + l)
-                            + BOX_PX_HEIGHT * (l + 1 + offset)
-                            + BOX_PX_HEIGHT / 3) as i32,
-                    ),
-                    TextStyle {
-                        font: FontDesc::new(
-                            FontFamily::Monospace,
-                            TEXT_SIZE as f64,
-                            FontStyle::Normal,
-                        ),
-                        color: BackendColor {
-                            alpha: 1.0,
-                            rgb: (0, 0, 0),
-                        },
-                        pos: Pos {
-                            h_pos: HPos::Center,
-                            v_pos: VPos::Center,
-                        },
-                    },
-                ))
-                .expect("couldn't write 'Argument:'");
-            backend
-                .draw(&Text::new(
-                    arg.name.to_string(),
-                    (
-                        ((BOX_PX_WIDTH * width + SEP_WIDTH + (1 + width) * BUFFER_WIDTH)
-                            * i
-                            + BUFFER_WIDTH * (k + 1)
-                            + BOX_PX_WIDTH * k
-                            + BOX_PX_WIDTH / 2) as i32,
-                        (HEADER_PX_HEIGHT
-                            + 2 * BUFFER_WIDTH
-                            + BUFFER_WIDTH * (1 + offset + l)
-                            + BOX_PX_HEIGHT * (l + 1 + offset)
-                            + 2 * BOX_PX_HEIGHT / 3) as i32,
-                    ),
-                    TextStyle {
-                        font: FontDesc::new(
-                            FontFamily::Monospace,
-                            TEXT_SIZE as f64,
-                            FontStyle::Normal,
-                        ),
-                        color: BackendColor {
-                            alpha: 1.0,
-                            rgb: (0, 0, 0),
-                        },
-                        pos: Pos {
-                            h_pos: HPos::Center,
-                            v_pos: VPos::Center,
-                        },
-                    },
-                ))
-                .expect("couldn't write argument");
+        draw_badge(backend, theme, &badge, theme.colors.foreground, theme.colors.background)?;
+        for seed in &pda.seeds {
+            draw_line(backend, theme, seed, FontStyle::Normal, text)
+                .map_err(|err| VizError::backend_write("couldn't write seed", err))?;
         }
     }
     Ok(())
 }
 
-/// Takes any nested `account_group` structure, flattens it, and
-/// returns all accounts within as a Vec<IdlAccounts>.
-fn unpack_group(account_group: IdlAccounts) -> Vec<IdlAccount> {
-    let mut v: Vec<IdlAccount> = vec![];
+/// Draws a type card with its kind and name like a box, then its lines.
+fn draw_type_card<DB: DrawingBackend>(
+    backend: &DrawingArea<DB, Shift>,
+    theme: &Theme,
+    card: &TypeCard,
+) -> Result<()> {
+    backend
+        .draw(&Rectangle::new(
+            corners(&card.rect),
+            Into::<ShapeStyle>::into(&rgb(theme.colors.card)).filled(),
+        ))
+        .and_then(|_| {
+            backend.draw(&Rectangle::new(
+                corners(&card.rect),
+                Into::<ShapeStyle>::into(&rgb(theme.colors.foreground)),
+            ))
+        })
+        .map_err(|err| VizError::backend_write("couldn't draw rect for type", err))?;
+    let label = card.label_lines.iter().map(|line| (line, FontStyle::Normal));
+    let name = card.name_lines.iter().map(|line| (line, FontStyle::Bold));
+    let lines = card.lines.iter().map(|line| (line, FontStyle::Normal));
+    for (line, style) in label.chain(name).chain(lines) {
+        draw_line(backend, theme, line, style, theme.colors.text)
+            .map_err(|err| VizError::backend_write("couldn't write type", err))?;
+    }
+    Ok(())
+}
 
-    for account in account_group.accounts.iter() {
-        match account {
-            IdlAccountItem::IdlAccount(idl_account) => v.push(idl_account.clone()),
-            IdlAccountItem::IdlAccounts(idl_accounts) => {
-                v.append(&mut unpack_group(idl_accounts.clone()))
-            }
+/// Draws the border of an account group with its name in bold on the top left.
+fn draw_group<DB: DrawingBackend>(
+    backend: &DrawingArea<DB, Shift>,
+    theme: &Theme,
+    group: &Group,
+) -> Result<()> {
+    backend
+        .draw(&Rectangle::new(
+            corners(&group.rect),
+            Into::<ShapeStyle>::into(&rgb(theme.colors.foreground)),
+        ))
+        .map_err(|err| VizError::backend_write("couldn't draw border for account group", err))?;
+    draw_line(backend, theme, &group.label, FontStyle::Bold, theme.colors.foreground)
+        .map_err(|err| VizError::backend_write("couldn't write account group name", err))?;
+    Ok(())
+}
+
+/// Draws a `fill` colored badge with its text in `text_color`.
+fn draw_badge<DB: DrawingBackend>(
+    backend: &DrawingArea<DB, Shift>,
+    theme: &Theme,
+    badge: &Badge,
+    fill: Color,
+    text_color: Color,
+) -> Result<()> {
+    backend
+        .draw(&Rectangle::new(
+            corners(&badge.rect),
+            Into::<ShapeStyle>::into(&rgb(fill)).filled(),
+        ))
+        .map_err(|err| VizError::backend_write("couldn't draw badge", err))?;
+    let line = Line {
+        text: badge.text.clone(),
+        x: badge.rect.left + 2,
+        y: badge.rect.top + badge.rect.height / 2,
+        align: Align::Left,
+        size: theme.font.badge,
+    };
+    draw_line(backend, theme, &line, FontStyle::Bold, text_color)
+        .map_err(|err| VizError::backend_write("couldn't write badge", err))?;
+    Ok(())
+}
+
+/// Flags the account box `rect` as unchecked by anchor with a border and a badge
+/// in its top left corner.
+fn draw_unchecked<DB: DrawingBackend>(
+    backend: &DrawingArea<DB, Shift>,
+    theme: &Theme,
+    rect: &Rect,
+) -> Result<()> {
+    for inset in 0..UNCHECKED_BORDER_PX_WIDTH {
+        let border = Rect {
+            left: rect.left + inset,
+            top: rect.top + inset,
+            width: rect.width - 2 * inset,
+            height: rect.height - 2 * inset,
         };
+        backend
+            .draw(&Rectangle::new(
+                corners(&border),
+                Into::<ShapeStyle>::into(&rgb(theme.colors.unchecked)),
+            ))
+            .map_err(|err| VizError::backend_write("couldn't draw unchecked border", err))?;
     }
-    v
+    let badge = Badge {
+        text: "UNCHECKED".to_string(),
+        rect: Rect {
+            left: rect.left + 1,
+            top: rect.top + 1,
+            width: layout::badge_px_width("UNCHECKED"),
+            height: layout::BADGE_PX_HEIGHT,
+        },
+    };
+    draw_badge(backend, theme, &badge, theme.colors.unchecked, theme.colors.text)
 }
@@ -0,0 +1,89 @@
+use anchor_viz::{error, viz};
+
+#[test]
+fn test_2_check_compat() {
+    use anchor_syn::idl::{IdlAccountItem, IdlTypeDefinitionTy};
+    use anchor_viz::compat::{self, CompatChange, Severity};
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let old = viz::Program::from_source(&lib, false).unwrap().idl;
+    let report = compat::check(&old, &old);
+    assert!(report.findings.is_empty() && report.is_compatible());
+    let changes = |change: &dyn Fn(&mut anchor_syn::idl::Idl)| {
+        let mut new = old.clone();
+        change(&mut new);
+        let report = compat::check(&old, &new);
+        let findings = report.findings.iter();
+        findings
+            .map(|finding| (finding.path.clone(), finding.change.clone()))
+            .collect::<Vec<_>>()
+    };
+    let path = |path: &str| path.to_string();
+
+    let moved = changes(&|idl| idl.instructions[2].accounts.swap(0, 1));
+    let order_changed = CompatChange::AccountOrderChanged { old: 1, new: 0 };
+    assert!(moved.contains(&(path("withdraw.owner"), order_changed)));
+    let new_account = changes(&|idl| {
+        let account = match &idl.instructions[0].accounts[1] {
+            IdlAccountItem::IdlAccount(account) => account.clone(),
+            IdlAccountItem::IdlAccounts(_) => unreachable!(),
+        };
+        idl.instructions[1]
+            .accounts
+            .push(IdlAccountItem::IdlAccount(account));
+    });
+    assert_eq!(
+        new_account,
+        [(path("deposit.owner"), CompatChange::AccountAdded)]
+    );
+    let reordered = changes(&|idl| idl.instructions[0].args.swap(0, 1));
+    let arg_reordered = CompatChange::ArgReordered { old: 0, new: 1 };
+    assert!(reordered.contains(&(path("open.vaultId"), arg_reordered)));
+    let renamed_arg = changes(&|idl| idl.instructions[1].args[0].name = "lamports".to_string());
+    let arg_renamed = CompatChange::ArgRenamed {
+        old: path("amount"),
+    };
+    assert_eq!(renamed_arg, [(path("deposit.lamports"), arg_renamed)]);
+    let renamed = changes(&|idl| idl.instructions[1].name = "depositAll".to_string());
+    let instruction_renamed = CompatChange::InstructionRenamed {
+        old: path("deposit"),
+    };
+    assert_eq!(renamed, [(path("depositAll"), instruction_renamed)]);
+    let relayout = changes(&|idl| match &mut idl.accounts[0].ty {
+        IdlTypeDefinitionTy::Struct { fields } => fields.push(fields[1].clone()),
+        IdlTypeDefinitionTy::Enum { .. } => unreachable!(),
+    });
+    assert!(matches!(
+        relayout[..],
+        [(_, CompatChange::TypeLayoutChanged { .. })]
+    ));
+    assert_eq!(relayout[0].0, "Vault");
+    let renamed_type = changes(&|idl| {
+        idl.types[0].name = "Kind".to_string();
+        idl.instructions[0].args[1].ty = anchor_syn::idl::IdlType::Defined("Kind".to_string());
+    });
+    let type_renamed = CompatChange::ArgTypeRenamed {
+        old: path("VaultKind"),
+        new: path("Kind"),
+    };
+    assert!(renamed_type.contains(&(path("open.kind"), type_renamed)));
+    let not_mut = changes(&|idl| match &mut idl.instructions[1].accounts[0] {
+        IdlAccountItem::IdlAccount(account) => account.is_mut = false,
+        IdlAccountItem::IdlAccounts(_) => unreachable!(),
+    });
+    assert_eq!(
+        not_mut,
+        [(path("deposit.vault"), CompatChange::NoLongerMut)]
+    );
+    assert_eq!(CompatChange::NoLongerMut.severity(), Severity::NonBreaking);
+
+    let mut new = old.clone();
+    new.instructions[1].args[0].ty = anchor_syn::idl::IdlType::U128;
+    let report = compat::check(&old, &new).to_string();
+    assert!(report.starts_with("test_2 0.1.0 → 0.1.0: 1 breaking, 0 non-breaking changes\n"));
+    assert!(report.contains("breaking      deposit.amount: arg type changed, u64 → u128\n"));
+
+    let (test_1, test_2) = ("programs/test_1/idl/test_1.json", lib.to_str().unwrap());
+    viz::check_compat(test_1, test_1, None, false).unwrap();
+    let err = viz::check_compat(test_1, test_2, None, false).unwrap_err();
+    assert_eq!(error::exit_code(&err), 9);
+}
@@ -0,0 +1,203 @@
+use anchor_viz::{error, theme, viz};
+
+#[test]
+fn test_2_diff() {
+    use anchor_syn::idl::IdlType;
+    use anchor_viz::diff::{self, Change, Status};
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let old = viz::Program::from_source(&lib, false).unwrap();
+    let mut new = viz::Program::from_source(&lib, false).unwrap();
+    new.idl.version = "0.2.0".to_string();
+    let mut close = new.idl.instructions[1].clone();
+    close.name = "close".to_string();
+    new.idl.instructions.push(close);
+    new.idl.instructions[1].args[0].ty = IdlType::U128;
+    new.idl.instructions[2].args.remove(0);
+    new.idl.instructions.remove(0);
+
+    let program_diff = diff::diff(&old.idl, &new.idl);
+    let statuses = [
+        Status::Unchanged,
+        Status::Added,
+        Status::Removed,
+        Status::Changed,
+    ];
+    assert_eq!(program_diff.statuses(), statuses);
+    assert_eq!(
+        program_diff.instruction("open").unwrap().status,
+        Status::Removed
+    );
+    assert_eq!(
+        program_diff.instruction("close").unwrap().status,
+        Status::Added
+    );
+    let deposit = program_diff.instruction("deposit").unwrap();
+    assert_eq!(deposit.status, Status::Changed);
+    let (old_ty, new_ty) = ("u64".to_string(), "u128".to_string());
+    let type_changed = Change::TypeChanged {
+        old: old_ty,
+        new: new_ty,
+    };
+    assert_eq!(deposit.arg("amount").unwrap().changes, [type_changed]);
+    assert_eq!(deposit.account("vault").unwrap().status, Status::Unchanged);
+    let withdraw = program_diff.instruction("withdraw").unwrap();
+    assert_eq!(withdraw.arg("vaultId").unwrap().status, Status::Removed);
+    assert_eq!(withdraw.arg("amount").unwrap().status, Status::Unchanged);
+
+    let mut moved = new.idl.clone();
+    moved.instructions[1].accounts.swap(0, 1);
+    let withdraw = diff::diff(&new.idl, &moved).instructions[1].clone();
+    let owner = withdraw.account("owner").unwrap();
+    assert_eq!(owner.changes, [Change::Moved { old: 1, new: 0 }]);
+
+    // removed instructions, accounts and args are drawn where they were
+    let merged = diff::merged(&old, &new);
+    let names: Vec<&str> = merged
+        .idl
+        .instructions
+        .iter()
+        .map(|ix| ix.name.as_str())
+        .collect();
+    assert_eq!(names, ["open", "deposit", "withdraw", "close"]);
+    let theme = theme::Theme::default();
+    let layout = anchor_viz::layout::layout(&merged, 2, false, false, viz::Fit::Shrink, &theme);
+    assert_eq!(layout.version.text, "Version: 0.1.0 → 0.2.0");
+    let listed: Vec<Status> = layout.statuses.iter().map(|entry| entry.status).collect();
+    assert_eq!(listed, statuses);
+    assert!(layout.roles.is_empty());
+    let status = |b: &anchor_viz::layout::LayoutBox| b.diff.as_ref().unwrap().status;
+    assert_eq!(status(&layout.columns[0].instruction), Status::Removed);
+    let arg = layout.columns[1]
+        .args
+        .iter()
+        .find(|b| b.name == "amount")
+        .unwrap();
+    assert_eq!(status(arg), Status::Changed);
+    assert_eq!(
+        layout.columns[1].footnotes[0].text,
+        "[1] amount: type u64 → u128"
+    );
+    let arg = layout.columns[2]
+        .args
+        .iter()
+        .find(|b| b.name == "vaultId")
+        .unwrap();
+    assert_eq!(status(arg), Status::Removed);
+
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    for format in [viz::Format::Svg, viz::Format::Png] {
+        let out = std::path::Path::new("target/viz-test/test_2-diff");
+        let out = out.with_extension(format.extension());
+        viz::render(&merged, &out, &viz::RenderOptions::new(format)).unwrap();
+    }
+    let out = std::path::Path::new("target/viz-test/test_2-diff.html");
+    let err = viz::render(&merged, out, &viz::RenderOptions::new(viz::Format::Html)).unwrap_err();
+    assert_eq!(error::exit_code(&err), 2);
+}
+
+#[test]
+fn test_2_diff_groups() {
+    use anchor_syn::idl::IdlAccountItem;
+    use anchor_viz::diff::{self, Status};
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let old = viz::Program::from_source(&lib, false).unwrap();
+    let mut new = viz::Program::from_source(&lib, false).unwrap();
+    // deposit's program accounts move out of their transfer.programs group, which is removed
+    let accounts = &mut new.idl.instructions[1].accounts;
+    let programs = match &mut accounts[1] {
+        IdlAccountItem::IdlAccounts(transfer) => transfer.accounts.pop().unwrap(),
+        IdlAccountItem::IdlAccount(_) => unreachable!(),
+    };
+    match programs {
+        IdlAccountItem::IdlAccounts(programs) => accounts.extend(programs.accounts),
+        IdlAccountItem::IdlAccount(_) => unreachable!(),
+    }
+
+    let program_diff = diff::diff(&old.idl, &new.idl);
+    let deposit = program_diff.instruction("deposit").unwrap();
+    let status = |path| deposit.account(path).unwrap().status;
+    assert_eq!(status("tokenProgram"), Status::Added);
+    assert_eq!(status("transfer.programs.tokenProgram"), Status::Removed);
+    assert_eq!(status("transfer.from"), Status::Unchanged);
+
+    // the removed account goes back into its group, which is added back too
+    let merged = diff::merged(&old, &new);
+    let (old_accounts, new_accounts) = (
+        &old.idl.instructions[1].accounts,
+        &new.idl.instructions[1].accounts,
+    );
+    assert_eq!(merged.idl.instructions[1].accounts[..2], old_accounts[..]);
+    assert_eq!(merged.idl.instructions[1].accounts[2..], new_accounts[2..]);
+    let theme = theme::Theme::default();
+    let layout = anchor_viz::layout::layout(&merged, 2, false, false, viz::Fit::Shrink, &theme);
+    let statuses: Vec<(&str, Status)> = layout.columns[1]
+        .accounts
+        .iter()
+        .filter(|b| b.name == "tokenProgram")
+        .map(|b| (b.path.as_str(), b.diff.as_ref().unwrap().status))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("tokenProgram", Status::Added),
+            ("transfer.programs.tokenProgram", Status::Removed)
+        ]
+    );
+}
+
+#[test]
+fn test_2_diff_git() {
+    use std::process::Command;
+    let repo = std::path::Path::new("target/viz-test/git/test_2");
+    let _ = std::fs::remove_dir_all(repo);
+    std::fs::create_dir_all(repo.join("src")).unwrap();
+    let lib = "programs/test_2/programs/test_2/src/lib.rs";
+    std::fs::copy(lib, repo.join("src/lib.rs")).unwrap();
+    std::fs::copy(
+        "programs/test_2/programs/test_2/Cargo.toml",
+        repo.join("Cargo.toml"),
+    )
+    .unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "test_2"]);
+    let source = std::fs::read_to_string(lib).unwrap();
+    let deposit = "pub fn deposit(ctx: Context<Deposit>, amount: u";
+    let changed = source.replace(&format!("{}64", deposit), &format!("{}32", deposit));
+    assert_ne!(changed, source);
+    std::fs::write(repo.join("src/lib.rs"), changed).unwrap();
+
+    let old = viz::Program::from_spec("HEAD", repo, None, false).unwrap();
+    let new = viz::Program::from_spec(".", repo, None, false).unwrap();
+    let head = viz::Program::from_rev(repo, "HEAD", None, false).unwrap();
+    assert_eq!(head.idl, old.idl);
+    // located in the repository, not in the removed checkout
+    let lib_rs = std::path::Path::new("src/lib.rs");
+    assert_eq!(head.source.locations["deposit"].file, lib_rs);
+    let findings = anchor_viz::lint::lint(&old);
+    assert!(!findings.is_empty());
+    for finding in &findings {
+        assert_eq!(finding.location.as_ref().unwrap().file, lib_rs);
+    }
+    let program_diff = anchor_viz::diff::diff(&old.idl, &new.idl);
+    let deposit = program_diff.instruction("deposit").unwrap();
+    let amount = deposit.arg("amount").unwrap();
+    assert_eq!(amount.changes[0].to_string(), "type u64 → u32");
+
+    let err = viz::Program::from_spec("no-such-rev", repo, None, false).unwrap_err();
+    assert_eq!(error::exit_code(&err), 8);
+    // the current dir has to be in the revision
+    std::fs::create_dir_all(repo.join("untracked")).unwrap();
+    let err = viz::Program::from_rev(&repo.join("untracked"), "HEAD", None, false).unwrap_err();
+    assert_eq!(error::exit_code(&err), 8);
+}
@@ -0,0 +1,95 @@
+use anchor_viz::{theme, viz};
+
+#[test]
+fn test_1_fit() {
+    use anchor_viz::layout::{self, text_px_width};
+    let theme = theme::Theme::default();
+    let (family, spacing) = (&theme.font.family, theme.sizes.spacing);
+    let mut program = viz::Program::from_idl_json("programs/test_1/idl/test_1.json").unwrap();
+    program.idl.instructions[0].name = "initialize_vault_with_oracle_config".to_string();
+
+    let shrunk = layout::layout(&program, 2, false, false, viz::Fit::Shrink, &theme);
+    let header = &shrunk.columns[0].instruction;
+    assert_eq!(header.rect.width, theme.sizes.box_width);
+    let names: Vec<&str> = header
+        .name_lines
+        .iter()
+        .map(|line| line.text.as_str())
+        .collect();
+    assert_eq!(names.concat(), "initialize_vault_with_oracle_config");
+    assert!(names.len() == 2 && names[0].ends_with('_'));
+    for line in header.label_lines.iter().chain(&header.name_lines) {
+        assert!(text_px_width(&line.text, family, line.size) <= header.rect.width - 2 * spacing);
+    }
+
+    let widened = layout::layout(&program, 2, false, false, viz::Fit::Widen, &theme);
+    let header = &widened.columns[0].instruction;
+    assert!(header.rect.width > theme.sizes.box_width);
+    assert_eq!(header.name_lines.len(), 1);
+    assert_eq!(header.name_lines[0].size, theme.font.text);
+
+    let (_, lines) = layout::fit_text(&"x".repeat(100), 100, family, theme.font.text, 1);
+    assert!(lines.len() == 1 && lines[0].ends_with('…'));
+}
+
+#[test]
+fn test_1_role_legend() {
+    use anchor_viz::layout;
+    use viz::Role;
+    let theme = theme::Theme::default();
+    let program = viz::Program::from_idl_json("programs/test_1/idl/test_1.json").unwrap();
+    let roles = [Role::Instruction, Role::Signer, Role::Mut, Role::Immut];
+    assert_eq!(layout::roles_present(&program.idl), roles);
+
+    let wide = layout::layout(&program, 2, false, false, viz::Fit::Shrink, &theme);
+    let listed: Vec<Role> = wide.roles.iter().map(|entry| entry.role).collect();
+    assert_eq!(listed, roles);
+    let top = theme.sizes.header + theme.sizes.spacing;
+    assert_eq!(wide.columns[0].instruction.rect.top, top);
+    assert!(wide
+        .roles
+        .iter()
+        .all(|entry| entry.swatch.top > wide.version.y));
+
+    // a single narrow column wraps the legend onto more lines, growing the header
+    let narrow = layout::layout(&program, 1, false, false, viz::Fit::Shrink, &theme);
+    assert!(narrow.columns[0].instruction.rect.top > top);
+    for entry in &narrow.roles {
+        assert!(entry.swatch.right() <= narrow.width);
+        assert!(entry.swatch.bottom() <= narrow.columns[0].instruction.rect.top);
+    }
+}
+
+#[test]
+fn test_2_types_only() {
+    use anchor_viz::layout;
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let mut program = viz::Program::from_source(&lib, false).unwrap();
+    program.idl.instructions.clear();
+    let theme = theme::Theme::default();
+
+    // no columns, but still room for the title and the types panel
+    let layout = layout::layout(&program, 1, false, false, viz::Fit::Shrink, &theme);
+    assert!(layout.columns.is_empty());
+    let types = layout.types.as_ref().unwrap();
+    let names: Vec<&str> = types.cards.iter().map(|card| card.name.as_str()).collect();
+    assert!(names.contains(&"Vault") && names.contains(&"VaultKind"));
+    assert!(types.cards.iter().all(|card| card.rect.right() <= layout.width));
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    let out = std::path::Path::new("target/viz-test/test_2-types.svg");
+    viz::render(&program, out, &viz::RenderOptions::new(viz::Format::Svg)).unwrap();
+}
+
+#[test]
+fn test_2_large_text() {
+    use anchor_viz::layout;
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let program = viz::Program::from_source(&lib, false).unwrap();
+    // the narrowest boxes a theme can have, with text too large for even one character
+    let mut theme = theme::Theme::default();
+    theme.sizes.box_width = 4 * theme.sizes.spacing + 1;
+    theme.font.text = 200;
+    let layout = layout::layout(&program, 2, false, false, viz::Fit::Shrink, &theme);
+    let amount = layout.columns[1].args.iter().find(|b| b.name == "amount");
+    assert_eq!(amount.unwrap().label, "…:");
+}
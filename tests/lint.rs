@@ -0,0 +1,148 @@
+use anchor_viz::{error, theme, viz};
+
+#[test]
+fn test_2_lint() {
+    use anchor_syn::idl::IdlAccountItem;
+    use anchor_viz::lint::{self, Rule};
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let mut program = viz::Program::from_source(&lib, false).unwrap();
+    let findings = lint::lint(&program);
+    let rules: Vec<(&str, Option<&str>, Rule)> = findings
+        .iter()
+        .map(|f| (f.instruction.as_str(), f.account.as_deref(), f.rule))
+        .collect();
+    // anchor only checks that an `Account<Vault>` is owned by the program, so the deposit vault
+    // could be anyone's, unlike the withdraw one with its seeds
+    assert_eq!(
+        rules[..3],
+        [
+            ("deposit", Some("vault"), Rule::UnconstrainedMut),
+            ("deposit", Some("transfer.from"), Rule::UnconstrainedMut),
+            ("deposit", Some("transfer.to"), Rule::UnconstrainedMut)
+        ]
+    );
+    assert!(rules.iter().all(|rule| rule.2 == Rule::UnconstrainedMut));
+    assert!(!rules.contains(&("withdraw", Some("vault"), Rule::UnconstrainedMut)));
+    let location = findings[0].location.as_ref().unwrap();
+    let source = std::fs::read_to_string(&lib).unwrap();
+    let deposit = source.find("pub struct Deposit").unwrap();
+    let line = source[deposit..]
+        .find("pub vault: Account")
+        .map(|offset| source[..deposit + offset].lines().count());
+    assert!(location.file.ends_with("src/lib.rs"));
+    assert_eq!(Some(location.line), line);
+    assert_eq!(
+        findings[0].to_string(),
+        format!(
+            "warning[unconstrained-mut]: `deposit.vault` is mutable but has no owner, has_one or \
+             seeds constraint\n  --> {}",
+            location
+        )
+    );
+
+    // marked on the box at the finding's path, not on others of the same name
+    let from = match &program.idl.instructions[1].accounts[1] {
+        IdlAccountItem::IdlAccounts(transfer) => transfer.accounts[0].clone(),
+        IdlAccountItem::IdlAccount(_) => unreachable!(),
+    };
+    program.idl.instructions[1].accounts.push(from);
+    program.lints = findings;
+    let theme = theme::Theme::default();
+    let layout = anchor_viz::layout::layout(&program, 2, false, false, viz::Fit::Shrink, &theme);
+    let from = |path: &str| {
+        let mut accounts = layout.columns[1].accounts.iter();
+        accounts.find(|b| b.path == path).unwrap()
+    };
+    let warning = from("transfer.from").warning.as_ref().unwrap();
+    assert_eq!(warning.text, "WARNING");
+    assert!(warning.rect.right() <= from("transfer.from").rect.right());
+    assert_eq!(
+        from("transfer.from").lints,
+        [program.lints[1].message.clone()]
+    );
+    assert!(from("from").warning.is_none() && from("from").lints.is_empty());
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    let out = std::path::Path::new("target/viz-test/test_2-lint.svg");
+    viz::render(&program, out, &viz::RenderOptions::new(viz::Format::Svg)).unwrap();
+    assert!(std::fs::read_to_string(out)
+        .unwrap()
+        .contains("warning: `deposit.transfer.from` is mutable"));
+    let out = std::path::Path::new("target/viz-test/test_2-lint.html");
+    let err = viz::render(&program, out, &viz::RenderOptions::new(viz::Format::Html)).unwrap_err();
+    assert_eq!(error::exit_code(&err), 2);
+
+    // the other rules, on a copy of the program with the pitfalls added
+    let dir = std::path::Path::new("target/viz-test/lint/test_2");
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::copy(
+        "programs/test_2/programs/test_2/Cargo.toml",
+        dir.join("Cargo.toml"),
+    )
+    .unwrap();
+    let mut source = source;
+    for (from, to) in [
+        ("    /// CHECK: only used as the cpi target\n", ""),
+        (
+            "    pub system_program: Program<'info, System>,\n}",
+            "    pub system_program: Program<'info, System>,\n    /// The rent sysvar.\n    \
+             /// CHECK: only read\n    pub rent: AccountInfo<'info>,\n}",
+        ),
+        (
+            "/// CHECK: token account, validated by the token program\n    #[account(mut)]\n    \
+             pub from",
+            "/// A token account, without a CHECK\n    #[account(mut)]\n    pub from",
+        ),
+        (
+            "pub owner: Signer<'info>,\n    pub system_program",
+            "pub owner: AccountInfo<'info>,\n    pub system_program",
+        ),
+        (
+            "has_one = owner,",
+            "has_one = owner,\n        close = owner,",
+        ),
+        (
+            "pub authority: Signer<'info>",
+            "pub authority: AccountInfo<'info>",
+        ),
+    ] {
+        assert!(source.contains(from), "{}", from);
+        source = source.replace(from, to);
+    }
+    std::fs::write(dir.join("src/lib.rs"), source).unwrap();
+    let program = viz::Program::from_spec(".", dir, None, false).unwrap();
+    let findings = lint::lint(&program);
+    let messages: Vec<String> = findings.iter().map(|f| f.message.clone()).collect();
+    for message in [
+        "`open.vault` is initialized with payer `owner`, which isn't a signer",
+        "`deposit.transfer.programs.tokenProgram` is an AccountInfo without a `/// CHECK:` comment \
+         saying why it's safe",
+        "`deposit.transfer.from` is an AccountInfo without a `/// CHECK:` comment saying why \
+         it's safe",
+        "`deposit.transfer.authority` is an AccountInfo without a `/// CHECK:` comment saying why \
+         it's safe",
+        "`deposit` has no signer, so anyone can call it",
+        "`withdraw.vault` is closed into `owner`, which isn't mutable",
+    ] {
+        assert!(messages.iter().any(|m| m == message), "{} not in {:#?}", message, messages);
+    }
+    assert!(!messages
+        .iter()
+        .any(|m| m.starts_with("`withdraw` has no signer")));
+    // a `CHECK:` on a later line of the doc comment counts too
+    assert!(!messages.iter().any(|m| m.contains(".rent`")));
+
+    let output = viz::Output::new(viz::Format::Svg);
+    let output = viz::Output {
+        template: "target/viz-test/{name}-lint.{format}".into(),
+        ..output
+    };
+    // an idl json only has the no-signer rule, which test_1 passes
+    let findings = viz::visual_lint("programs/test_1/idl/test_1.json", None, &output).unwrap();
+    assert!(findings.is_empty());
+    let findings = viz::visual_lint(lib.to_str().unwrap(), None, &output).unwrap();
+    let messages = |findings: &[lint::Finding]| -> Vec<String> {
+        findings.iter().map(|f| f.message.clone()).collect()
+    };
+    let program = viz::Program::from_source(&lib, false).unwrap();
+    assert_eq!(messages(&findings), messages(&lint::lint(&program)));
+}
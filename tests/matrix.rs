@@ -0,0 +1,63 @@
+use anchor_viz::{error, theme, viz};
+
+#[test]
+fn test_2_matrix() {
+    use anchor_viz::matrix::{self, Access};
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let program = viz::Program::from_source(&lib, false).unwrap();
+    let account_matrix = matrix::account_matrix(&program.idl);
+    assert_eq!(account_matrix.instructions, ["open", "deposit", "withdraw"]);
+    let accounts = [
+        "vault",
+        "owner",
+        "systemProgram",
+        "from",
+        "to",
+        "authority",
+        "tokenProgram",
+    ];
+    assert_eq!(account_matrix.accounts, accounts);
+    let owner = Access {
+        signer: true,
+        writable: true,
+    };
+    assert_eq!(account_matrix.cells[0][1], Some(owner));
+    assert_eq!(account_matrix.cells[1][1], None);
+    assert_eq!(account_matrix.cells[2][1].unwrap().code(), "S");
+    let csv = account_matrix.csv();
+    let mut lines = csv.lines();
+    let header = "instruction,vault,owner,systemProgram,from,to,authority,tokenProgram";
+    assert_eq!(lines.next(), Some(header));
+    assert_eq!(lines.next(), Some("open,W,SW,R,,,,"));
+
+    let theme = theme::Theme::default();
+    let layout = matrix::layout(&account_matrix, &theme);
+    assert_eq!(layout.cells.len(), 16);
+    assert!(layout
+        .cells
+        .iter()
+        .all(|cell| cell.rect.right() <= layout.width));
+    assert!(layout
+        .cells
+        .iter()
+        .all(|cell| cell.rect.bottom() <= layout.height));
+
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    for format in [viz::Format::Png, viz::Format::Svg, viz::Format::Csv] {
+        let out = std::path::Path::new("target/viz-test/test_2-matrix");
+        let out = out.with_extension(format.extension());
+        viz::render_matrix(&program, &out, &viz::RenderOptions::new(format)).unwrap();
+    }
+    let out = std::path::Path::new("target/viz-test/test_2-matrix.csv");
+    assert_eq!(std::fs::read_to_string(out).unwrap(), csv);
+    let out = std::path::Path::new("target/viz-test/test_2-matrix.html");
+    let err =
+        viz::render_matrix(&program, out, &viz::RenderOptions::new(viz::Format::Html)).unwrap_err();
+    assert_eq!(error::exit_code(&err), 2);
+
+    // the usual columns can't be written as csv
+    let out = std::path::Path::new("target/viz-test/test_2.csv");
+    let err = viz::render(&program, out, &viz::RenderOptions::new(viz::Format::Csv)).unwrap_err();
+    assert_eq!(error::exit_code(&err), 2);
+    assert!(err.to_string().starts_with("a visualization can't be written as csv"));
+}
@@ -0,0 +1,174 @@
+use anchor_viz::{theme, viz};
+
+#[test]
+fn test_2_constraints() {
+    let source = anchor_viz::source::SourceInfo::parse(std::path::Path::new(
+        "programs/test_2/programs/test_2/src/lib.rs",
+    ))
+    .unwrap();
+    let open_vault = source.accounts("open").field("vault").unwrap();
+    assert_eq!(open_vault.badges(), ["I", "P:owner", "SP", "S"]);
+    let withdraw_vault = source.accounts("withdraw").field("vault").unwrap();
+    assert_eq!(withdraw_vault.badges(), ["S", "H:owner", "C"]);
+    let from = source
+        .accounts("withdraw")
+        .group("transfer")
+        .field("from")
+        .unwrap();
+    assert!(from.badges().is_empty());
+}
+
+#[test]
+fn test_source_modules() {
+    // same-named accounts structs in different modules, and one that anchor rejects
+    let dir = std::path::Path::new("target/viz-test/source/src");
+    std::fs::create_dir_all(dir).unwrap();
+    let lib = r#"
+use anchor_lang::prelude::*;
+
+mod first;
+
+pub mod second {
+    use super::*;
+
+    #[derive(Accounts)]
+    pub struct Update<'info> {
+        #[account(mut, has_one = owner)]
+        pub data: Account<'info, Data>,
+        pub owner: Signer<'info>,
+    }
+}
+
+#[derive(Accounts)]
+pub struct Broken<'info> {
+    #[account(init, space = 8)]
+    pub data: Account<'info, Data>,
+}
+
+#[program]
+pub mod modules {
+    use super::*;
+    pub fn create(ctx: Context<first::Update>) -> Result<()> { Ok(()) }
+    pub fn update(ctx: Context<second::Update>) -> Result<()> { Ok(()) }
+    pub fn broken(ctx: Context<Broken>) -> Result<()> { Ok(()) }
+}
+"#;
+    let first = r#"
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct Update<'info> {
+    #[account(init, payer = payer, space = 8)]
+    pub data: Account<'info, Data>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+"#;
+    std::fs::write(dir.join("lib.rs"), lib).unwrap();
+    std::fs::write(dir.join("first.rs"), first).unwrap();
+    let source = anchor_viz::source::SourceInfo::parse(&dir.join("lib.rs")).unwrap();
+    let data = source.accounts("create").field("data").unwrap();
+    assert_eq!(data.badges(), ["I", "P:payer", "SP"]);
+    assert!(data.location.as_ref().unwrap().file.ends_with("first.rs"));
+    let data = source.accounts("update").field("data").unwrap();
+    assert_eq!(data.badges(), ["H:owner"]);
+    assert!(data.location.as_ref().unwrap().file.ends_with("lib.rs"));
+    assert!(source.accounts("broken").fields.is_empty());
+    assert!(source.locations.contains_key("broken"));
+}
+
+#[test]
+fn test_2_types() {
+    let source = anchor_viz::source::SourceInfo::parse(std::path::Path::new(
+        "programs/test_2/programs/test_2/src/lib.rs",
+    ))
+    .unwrap();
+    let open = source.accounts("open");
+    let vault = open.field("vault").unwrap();
+    assert_eq!(vault.ty.as_deref(), Some("Account<Vault>"));
+    assert!(!vault.is_unchecked());
+    assert_eq!(open.field("owner").unwrap().ty.as_deref(), Some("Signer"));
+    assert_eq!(
+        open.field("systemProgram").unwrap().ty.as_deref(),
+        Some("Program<System>")
+    );
+    let from = source
+        .accounts("deposit")
+        .group("transfer")
+        .field("from")
+        .unwrap();
+    assert_eq!(from.ty.as_deref(), Some("AccountInfo"));
+    assert!(from.is_unchecked());
+}
+
+#[test]
+fn test_2_docs() {
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let program = viz::Program::from_source(&lib, false).unwrap();
+    let withdraw = program.source.docs("withdraw");
+    let doc = "Moves `amount` out of the vault, which must hold at least that much.";
+    assert_eq!(withdraw.doc.as_deref(), Some(doc));
+    assert_eq!(
+        withdraw.args["vaultId"],
+        "id of the vault among those of the owner"
+    );
+    assert_eq!(withdraw.args["amount"], "lamports to withdraw");
+    assert!(program.source.docs("deposit").doc.is_none());
+    let open = program.source.accounts("open");
+    assert_eq!(
+        open.field("owner").unwrap().doc.as_deref(),
+        Some("Pays for the vault account")
+    );
+    let from = program
+        .source
+        .accounts("withdraw")
+        .group("transfer")
+        .field("from")
+        .unwrap();
+    let check = "CHECK: token account, validated by the token program";
+    assert_eq!(from.doc.as_deref(), Some(check));
+
+    let theme = theme::Theme::default();
+    let plain = anchor_viz::layout::layout(&program, 2, false, false, viz::Fit::Shrink, &theme);
+    let layout = anchor_viz::layout::layout(&program, 2, false, true, viz::Fit::Shrink, &theme);
+    assert!(plain
+        .columns
+        .iter()
+        .all(|column| column.footnotes.is_empty()));
+    assert!(layout.height > plain.height);
+    let column = &layout.columns[2];
+    assert_eq!(column.instruction.footnote, Some(1));
+    assert_eq!(column.instruction.label_lines[0].text, "Instruction: [1]");
+    assert!(column.footnotes[0]
+        .text
+        .starts_with("[1] withdraw: Moves `amount`"));
+    let boxes_bottom = column.boxes().map(|b| b.rect.bottom()).max().unwrap();
+    assert!(column.footnotes.iter().all(|line| line.y > boxes_bottom));
+    let footnoted: Vec<usize> = column.boxes().filter_map(|b| b.footnote).collect();
+    assert_eq!(footnoted, (1..=footnoted.len()).collect::<Vec<_>>());
+
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    let out = std::path::Path::new("target/viz-test/test_2-docs.svg");
+    viz::render(
+        &program,
+        out,
+        &viz::RenderOptions {
+            footnotes: true,
+            ..viz::RenderOptions::new(viz::Format::Svg)
+        },
+    )
+    .unwrap();
+    let svg = std::fs::read_to_string(out).unwrap();
+    assert!(svg.contains("<title>lamports to withdraw</title>"));
+    let out = std::path::Path::new("target/viz-test/test_2-docs.png");
+    viz::render(
+        &program,
+        out,
+        &viz::RenderOptions {
+            footnotes: true,
+            ..viz::RenderOptions::new(viz::Format::Png)
+        },
+    )
+    .unwrap();
+}
@@ -0,0 +1,54 @@
+use anchor_viz::{error, theme, viz};
+
+#[test]
+fn test_themes() {
+    use theme::Theme;
+    for name in theme::BUILTIN_THEMES {
+        assert_eq!(Theme::load(name).unwrap(), Theme::builtin(name).unwrap());
+    }
+    std::fs::create_dir_all("target/viz-test/workspace").unwrap();
+    let path = "target/viz-test/theme.toml";
+    let theme_toml = "theme = \"dark\"\n[colors]\nmut = \"#123456\"\n[sizes]\nbox_width = 300\n";
+    std::fs::write(path, theme_toml).unwrap();
+    let custom = Theme::load(path).unwrap();
+    let dark = Theme::builtin("dark").unwrap();
+    assert_eq!(custom.colors.mutable.hex(), "#123456");
+    assert_eq!(custom.colors.background, dark.colors.background);
+    assert_eq!(custom.sizes.box_width, 300);
+
+    let program = viz::Program::from_idl_json("programs/test_1/idl/test_1.json").unwrap();
+    let layout = anchor_viz::layout::layout(&program, 2, false, false, viz::Fit::Shrink, &custom);
+    assert_eq!(layout.columns[0].instruction.rect.width, 300);
+    let out = std::path::Path::new("target/viz-test/test_1-dark.png");
+    viz::render(
+        &program,
+        out,
+        &viz::RenderOptions {
+            theme: custom.clone(),
+            ..viz::RenderOptions::new(viz::Format::Png)
+        },
+    )
+    .unwrap();
+
+    // the [viz] section of Anchor.toml, found from a subdirectory
+    let anchor_toml = "[viz]\ntheme = \"colorblind-safe\"\n[viz.font]\ntitle = 30\n";
+    std::fs::write("target/viz-test/workspace/Anchor.toml", anchor_toml).unwrap();
+    let programs = std::path::Path::new("target/viz-test/workspace/programs");
+    let workspace = Theme::from_workspace(programs);
+    let colorblind_safe = Theme::builtin("colorblind-safe").unwrap();
+    assert_eq!(workspace.unwrap().colors, colorblind_safe.colors);
+
+    std::fs::write(path, "[colors]\nmut = \"red\"\n").unwrap();
+    assert_eq!(error::exit_code(&Theme::load(path).unwrap_err()), 2);
+    std::fs::write(path, "[sizes]\nbox_widht = 300\n").unwrap();
+    assert_eq!(error::exit_code(&Theme::load(path).unwrap_err()), 2);
+    // no room for the title and the color legend
+    std::fs::write(path, "[sizes]\nheader = 20\n").unwrap();
+    let err = Theme::load(path).unwrap_err();
+    assert_eq!(error::exit_code(&err), 2);
+    assert!(err.to_string().contains("sizes.header must be at least 52"), "{}", err);
+    assert_eq!(
+        error::exit_code(&Theme::load("no_such_theme").unwrap_err()),
+        2
+    );
+}
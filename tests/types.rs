@@ -0,0 +1,63 @@
+use anchor_viz::viz;
+
+#[test]
+fn test_2_type_defs() {
+    use anchor_syn::idl::IdlType;
+    use anchor_viz::types::{self, TypeKind};
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let program = viz::Program::from_source(&lib, false).unwrap();
+    let defs = types::type_defs(&program);
+    let names: Vec<&str> = defs.iter().map(|def| def.name.as_str()).collect();
+    assert_eq!(names, ["Vault", "VaultKind"]);
+
+    let vault = &defs[0];
+    assert_eq!(vault.kind, TypeKind::Account);
+    // discriminator + owner + balance + kind
+    assert_eq!(vault.size.to_string(), "50 bytes");
+    assert_eq!(vault.fields.len(), 3);
+    assert_eq!(
+        vault.used_by,
+        ["open.vault", "deposit.vault", "withdraw.vault"]
+    );
+
+    let kind = &defs[1];
+    assert_eq!(kind.kind, TypeKind::Enum);
+    assert_eq!(kind.size.to_string(), "2 bytes");
+    assert_eq!(kind.variants[1].fields[0].name, "members");
+    assert_eq!(kind.used_by, ["open.kind", "Vault.kind"]);
+
+    let keys = IdlType::Vec(Box::new(IdlType::PublicKey));
+    assert_eq!(
+        types::borsh_size(&keys, &program.idl).to_string(),
+        "4+ bytes"
+    );
+    let kinds = IdlType::Option(Box::new(IdlType::Array(
+        Box::new(IdlType::Defined("VaultKind".to_string())),
+        3,
+    )));
+    assert_eq!(
+        types::borsh_size(&kinds, &program.idl).to_string(),
+        "7 bytes"
+    );
+}
+
+#[test]
+fn test_type_labels() {
+    use anchor_syn::idl::IdlType;
+    use anchor_viz::types::{abbreviated_type_label, type_label};
+    let nested = IdlType::Option(Box::new(IdlType::Vec(Box::new(IdlType::Defined(
+        "MyStruct".to_string(),
+    )))));
+    assert_eq!(type_label(&nested), "Option<Vec<MyStruct>>");
+    assert_eq!(
+        type_label(&IdlType::Array(Box::new(IdlType::U8), 32)),
+        "[u8; 32]"
+    );
+    assert_eq!(type_label(&IdlType::PublicKey), "Pubkey");
+    assert_eq!(type_label(&IdlType::I128), "i128");
+
+    assert_eq!(abbreviated_type_label(&nested, 21), "Option<Vec<MyStruct>>");
+    assert_eq!(abbreviated_type_label(&nested, 16), "Option<Vec<…>>");
+    assert_eq!(abbreviated_type_label(&nested, 10), "Option<…>");
+    assert_eq!(abbreviated_type_label(&nested, 5), "Opti…");
+}
@@ -0,0 +1,300 @@
+use anchor_syn::idl::IdlAccountItem;
+use anchor_viz::{error, theme, viz};
+
+/// Output of the given format written under `target/viz-test`, out of the way of the sources.
+fn test_output(format: viz::Format) -> viz::Output {
+    viz::Output {
+        dir: "target/viz-test".into(),
+        ..viz::Output::new(format)
+    }
+}
+
+/// Whether the file at `path` is a png image.
+fn is_png(path: &str) -> bool {
+    let bytes = std::fs::read(path).unwrap();
+    bytes.starts_with(b"\x89PNG\r\n\x1a\n")
+}
+
+/// Whether `name` is a text line of `svg`, i.e. drawn as the whole text of a box or label.
+fn drawn(svg: &str, name: &str) -> bool {
+    svg.lines().any(|line| line == name)
+}
+
+/// Names of the instructions and accounts of the idl of test_2's source.
+fn test_2_names() -> Vec<String> {
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let idl = viz::Program::from_source(&lib, false).unwrap().idl;
+    let mut names = vec![];
+    for ix in &idl.instructions {
+        names.push(ix.name.clone());
+        let mut items: Vec<&IdlAccountItem> = ix.accounts.iter().collect();
+        while let Some(item) = items.pop() {
+            match item {
+                IdlAccountItem::IdlAccount(account) => names.push(account.name.clone()),
+                IdlAccountItem::IdlAccounts(group) => items.extend(&group.accounts),
+            }
+        }
+    }
+    names
+}
+
+#[test]
+fn test_0() {
+    viz::visual(
+        Some("test_0/programs/test_0".to_string()),
+        None,
+        &test_output(viz::Format::Png),
+    )
+    .unwrap();
+    assert!(is_png("target/viz-test/test_0.png"));
+}
+
+#[test]
+fn test_1() {
+    viz::visual(
+        Some("test_1/programs/test_1".to_string()),
+        None,
+        &test_output(viz::Format::Png),
+    )
+    .unwrap();
+    assert!(is_png("target/viz-test/test_1.png"));
+}
+
+#[test]
+fn test_1_svg() {
+    viz::visual(
+        Some("test_1/programs/test_1".to_string()),
+        None,
+        &test_output(viz::Format::Svg),
+    )
+    .unwrap();
+    let svg = std::fs::read_to_string("target/viz-test/test_1.svg").unwrap();
+    assert!(svg.starts_with("<svg"));
+    let idl = viz::load_idl("programs/test_1/idl/test_1.json").unwrap();
+    for ix in &idl.instructions {
+        assert!(drawn(&svg, &ix.name), "{} not drawn", ix.name);
+    }
+}
+
+#[test]
+fn test_2() {
+    viz::visual(
+        Some("test_2/programs/test_2".to_string()),
+        None,
+        &test_output(viz::Format::Png),
+    )
+    .unwrap();
+    assert!(is_png("target/viz-test/test_2.png"));
+}
+
+#[test]
+fn test_2_seeds() {
+    let output = test_output(viz::Format::Svg);
+    let options = viz::RenderOptions {
+        seeds: true,
+        ..output.options
+    };
+    let output = viz::Output { options, ..output };
+    viz::visual(Some("test_2/programs/test_2".to_string()), None, &output).unwrap();
+    let svg = std::fs::read_to_string("target/viz-test/test_2.svg").unwrap();
+    for name in test_2_names() {
+        assert!(drawn(&svg, &name), "{} not drawn", name);
+    }
+    // the seeds of the vault: b"vault", owner.key(), vault_id
+    for seed in ["- &quot;vault&quot;", "- account: owner", "- arg: vault_id"] {
+        assert!(svg.contains(seed), "{} not drawn", seed);
+    }
+}
+
+#[test]
+fn test_2_html() {
+    let output = test_output(viz::Format::Html);
+    let options = viz::RenderOptions {
+        seeds: true,
+        ..output.options
+    };
+    let output = viz::Output { options, ..output };
+    viz::visual(Some("test_2/programs/test_2".to_string()), None, &output).unwrap();
+    let html = std::fs::read_to_string("target/viz-test/test_2.html").unwrap();
+    for name in test_2_names() {
+        assert!(
+            html.contains(&format!("\"name\":\"{}\"", name)),
+            "{} missing",
+            name
+        );
+    }
+    assert!(html.contains("\"seeds\":[{\"kind\":\"const\""));
+}
+
+#[test]
+fn test_2_mermaid() {
+    viz::visual(
+        Some("test_2/programs/test_2".to_string()),
+        None,
+        &test_output(viz::Format::Mermaid),
+    )
+    .unwrap();
+    let chart = std::fs::read_to_string("target/viz-test/test_2.mmd").unwrap();
+    assert!(chart.starts_with("flowchart"));
+    // a node per account of each instruction, with its name
+    let names = test_2_names();
+    let nodes: Vec<&str> = chart
+        .lines()
+        .filter(|line| line.contains("Account:<br/>") || line.contains("Signer:<br/>"))
+        .collect();
+    assert_eq!(nodes.len(), names.len() - 3);
+    for ix in ["open", "deposit", "withdraw"] {
+        assert!(chart.contains(&format!("Instruction:<br/>{}\"]", ix)));
+    }
+}
+
+#[test]
+fn test_2_dot() {
+    viz::visual(
+        Some("test_2/programs/test_2".to_string()),
+        None,
+        &test_output(viz::Format::Dot),
+    )
+    .unwrap();
+    let graph = std::fs::read_to_string("target/viz-test/test_2.dot").unwrap();
+    // used by both deposit and withdraw, drawn once
+    assert_eq!(
+        graph.matches("\"account:vault\" [label=\"vault\"").count(),
+        1
+    );
+}
+
+#[test]
+fn test_2_layout_json() {
+    viz::visual(
+        Some("test_2/programs/test_2".to_string()),
+        None,
+        &test_output(viz::Format::LayoutJson),
+    )
+    .unwrap();
+    let layout: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("target/viz-test/test_2.layout.json").unwrap(),
+    )
+    .unwrap();
+    let columns = layout["columns"].as_array().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(layout["separators"].as_array().unwrap().len(), 2);
+    assert_eq!(columns[0]["instruction"]["name"], "open");
+    assert_eq!(columns[0]["instruction"]["role"], "instruction");
+}
+
+#[test]
+fn test_2_library() {
+    let lib = viz::find_program(std::path::Path::new("programs/test_2"), Some("test_2")).unwrap();
+    let program = viz::Program::from_source(&lib, false).unwrap();
+    assert_eq!(program.idl.name, "test_2");
+    assert!(program.source.accounts("withdraw").field("vault").is_some());
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    let out = std::path::Path::new("target/viz-test/test_2-library.svg");
+    let options = viz::RenderOptions {
+        theme: theme::Theme::default(),
+        footnotes: true,
+        ..viz::RenderOptions::new(viz::Format::Svg)
+    };
+    viz::render(&program, out, &options.with_width(3).unwrap()).unwrap();
+    assert!(out.exists());
+}
+
+#[test]
+fn test_1_all() {
+    let output = viz::Output {
+        dir: "target/viz-test/all".into(),
+        ..viz::Output::new(viz::Format::Svg)
+    };
+    viz::visual_all(std::path::Path::new("programs/test_1"), &output).unwrap();
+    let index = std::fs::read_to_string("target/viz-test/all/anchor-viz.md").unwrap();
+    assert!(index.contains("\n![test_1](test_1.svg)\n"), "{}", index);
+
+    // only images are embedded in the index
+    let output = viz::Output {
+        options: viz::RenderOptions::new(viz::Format::Html),
+        ..output
+    };
+    viz::visual_all(std::path::Path::new("programs/test_1"), &output).unwrap();
+    let index = std::fs::read_to_string("target/viz-test/all/anchor-viz.md").unwrap();
+    assert!(index.contains("\n[test_1](test_1.html)\n"), "{}", index);
+    let output = viz::Output {
+        options: viz::RenderOptions::new(viz::Format::Mermaid),
+        ..output
+    };
+    viz::visual_all(std::path::Path::new("programs/test_1"), &output).unwrap();
+    let index = std::fs::read_to_string("target/viz-test/all/anchor-viz.md").unwrap();
+    assert!(index.contains("\n```mermaid\nflowchart"), "{}", index);
+}
+
+#[test]
+fn test_1_out() {
+    let output = viz::Output {
+        template: "{name}-{version}.{format}".to_string(),
+        dir: "target/viz-test".into(),
+        options: viz::RenderOptions {
+            fit: viz::Fit::Shrink,
+            footnotes: false,
+            theme: theme::Theme::default(),
+            ..viz::RenderOptions::new(viz::Format::Svg)
+        },
+    };
+    viz::visual(Some("test_1/programs/test_1".to_string()), None, &output).unwrap();
+    assert!(std::path::Path::new("target/viz-test/test_1-0.1.0.svg").exists());
+}
+
+#[test]
+fn test_invalid_width() {
+    let err = viz::RenderOptions::default().with_width(0).unwrap_err();
+    assert_eq!(error::exit_code(&err), 2);
+    let options = viz::RenderOptions::default().with_width(1).unwrap();
+    assert_eq!(options.width.get(), 1);
+}
+
+#[test]
+fn test_program_not_found() {
+    let err = viz::visual(
+        Some("no_such_program".to_string()),
+        None,
+        &test_output(viz::Format::Png),
+    )
+    .unwrap_err();
+    assert_eq!(error::exit_code(&err), 3);
+}
+
+#[test]
+fn test_invalid_idl() {
+    std::fs::create_dir_all("target/viz-test").unwrap();
+    let path = "target/viz-test/invalid.json";
+    std::fs::write(path, "{\"version\": ").unwrap();
+    let err = viz::load_idl(path).unwrap_err();
+    assert_eq!(error::exit_code(&err), 4);
+    // the serde error is kept as the cause
+    let causes: Vec<String> = err.chain().map(ToString::to_string).collect();
+    assert_eq!(causes.len(), 2);
+    assert!(causes[1].contains("EOF"), "{:?}", causes);
+    assert_eq!(
+        format!("{:#}", err),
+        format!("couldn't parse an idl from {}: {}", path, causes[1])
+    );
+}
+
+#[test]
+fn test_1_idl() {
+    let output = viz::Output {
+        dir: "target/viz-test/idl".into(),
+        ..viz::Output::new(viz::Format::Svg)
+    };
+    viz::visual(
+        None,
+        Some("programs/test_1/idl/test_1.json".to_string()),
+        &output,
+    )
+    .unwrap();
+    // the same instructions as from the source
+    let svg = std::fs::read_to_string("target/viz-test/idl/test_1.svg").unwrap();
+    let idl = viz::load_idl("programs/test_1/idl/test_1.json").unwrap();
+    for ix in &idl.instructions {
+        assert!(drawn(&svg, &ix.name), "{} not drawn", ix.name);
+    }
+}